@@ -0,0 +1,155 @@
+//! Compares recording values one by one against the bulk recording APIs, and into dense
+//! slots against sparse ones. For `AtomicHistogram`, compares recording against incrementing
+//! the atomic bucket alone, i.e. the cost of the exact summary, from one and several threads.
+//!
+//! Run with `cargo bench --bench histogram`.
+
+#![feature(test)]
+
+extern crate test;
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use databend_base::histogram::AtomicHistogram;
+use databend_base::histogram::Histogram;
+use databend_base::histogram::LOG_SCALE;
+use test::Bencher;
+use test::black_box;
+
+const N: usize = 10_000;
+
+const THREADS: usize = 4;
+
+/// Log-uniformly distributed values, so that every bucket range is exercised.
+fn values() -> Vec<u64> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..N)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state >> (state % 64)
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_record_loop(b: &mut Bencher) {
+    let values = values();
+    let mut hist: Histogram = Histogram::new();
+    b.iter(|| {
+        for &v in &values {
+            hist.record(black_box(v));
+        }
+    });
+}
+
+/// Multi-slot histograms store their slots sparse while few buckets are used: records the
+/// same values into a slot that stays sparse.
+#[bench]
+fn bench_record_loop_sparse(b: &mut Bencher) {
+    let values: Vec<u64> = values().into_iter().map(|v| 1000 + v % 1000).collect();
+    let mut hist: Histogram = Histogram::with_slots(2);
+    b.iter(|| {
+        for &v in &values {
+            hist.record(black_box(v));
+        }
+    });
+}
+
+/// Same values as [`bench_record_loop_sparse`], into the dense slot of a single-slot histogram.
+#[bench]
+fn bench_record_loop_dense(b: &mut Bencher) {
+    let values: Vec<u64> = values().into_iter().map(|v| 1000 + v % 1000).collect();
+    let mut hist: Histogram = Histogram::new();
+    b.iter(|| {
+        for &v in &values {
+            hist.record(black_box(v));
+        }
+    });
+}
+
+#[bench]
+fn bench_record_slice(b: &mut Bencher) {
+    let values = values();
+    let mut hist: Histogram = Histogram::new();
+    b.iter(|| hist.record_slice(black_box(&values)));
+}
+
+#[bench]
+fn bench_record_same_value_loop(b: &mut Bencher) {
+    let mut hist: Histogram = Histogram::new();
+    b.iter(|| {
+        for _ in 0..N {
+            hist.record(black_box(1000));
+        }
+    });
+}
+
+#[bench]
+fn bench_record_n(b: &mut Bencher) {
+    let mut hist: Histogram = Histogram::new();
+    b.iter(|| hist.record_n(black_box(1000), N as u64));
+}
+
+#[bench]
+fn bench_atomic_record_loop(b: &mut Bencher) {
+    let values = values();
+    let hist: AtomicHistogram = AtomicHistogram::new();
+    b.iter(|| {
+        for &v in &values {
+            hist.record(black_box(v));
+        }
+    });
+}
+
+/// Baseline for [`bench_atomic_record_loop`]: only the bucket increment, without the summary.
+#[bench]
+fn bench_atomic_buckets_only_loop(b: &mut Bencher) {
+    let values = values();
+    let buckets: Vec<AtomicU64> = (0..LOG_SCALE.num_buckets()).map(|_| AtomicU64::new(0)).collect();
+    b.iter(|| {
+        for &v in &values {
+            buckets[LOG_SCALE.calculate_bucket(black_box(v))].fetch_add(1, Ordering::Relaxed);
+        }
+    });
+}
+
+/// Records the values from each of [`THREADS`] threads into one histogram.
+#[bench]
+fn bench_atomic_record_threads(b: &mut Bencher) {
+    let values = values();
+    let hist: AtomicHistogram = AtomicHistogram::new();
+    b.iter(|| {
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for &v in &values {
+                        hist.record(black_box(v));
+                    }
+                });
+            }
+        })
+    });
+}
+
+/// Baseline for [`bench_atomic_record_threads`]: only the bucket increments.
+#[bench]
+fn bench_atomic_buckets_only_threads(b: &mut Bencher) {
+    let values = values();
+    let buckets: Vec<AtomicU64> = (0..LOG_SCALE.num_buckets()).map(|_| AtomicU64::new(0)).collect();
+    b.iter(|| {
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| {
+                    for &v in &values {
+                        buckets[LOG_SCALE.calculate_bucket(black_box(v))]
+                            .fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        })
+    });
+}
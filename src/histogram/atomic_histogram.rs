@@ -0,0 +1,379 @@
+use std::mem;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use super::bucket_scale::HistogramScale;
+use super::histogram::Histogram;
+use super::histogram_snapshot::HistogramSnapshot;
+use super::log_scale::LogScale;
+use super::percentile_stats::PercentileStats;
+use super::summary::Summary;
+
+/// Upper bound on the number of summary shards of an [`AtomicHistogram`].
+const MAX_SHARDS: usize = 64;
+
+/// Hands out a shard index to each thread that records, in turn.
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A histogram that can be shared and recorded to from many threads.
+///
+/// Uses the same logarithmic bucketing as [`Histogram`] with the same `WIDTH`, but each bucket is
+/// an [`AtomicU64`], so [`record()`](Self::record) only needs `&self`.
+///
+/// Exact min, max, sum and sum of squares are tracked as well, see [`Summary`]. They are split
+/// into shards, one per available core, each behind its own lock on its own cache line. A
+/// record holds the lock of its thread's shard while it updates both its bucket and that
+/// shard's summary, so the summaries always describe exactly the values in the buckets. A
+/// thread always records into the same shard, so the lock is in practice uncontended: a record
+/// costs an uncontended lock plus a relaxed atomic increment.
+///
+/// Percentiles are not computed on the atomic buckets directly: take a
+/// [`snapshot()`](Self::snapshot) to get a plain single-slot [`Histogram`] and query it.
+///
+/// # Consistency
+///
+/// A snapshot reads the buckets and the summaries under the locks of all shards, so it holds
+/// exactly the values recorded before it: its total equals the sum of its buckets, and its summary
+/// describes the same values. Values recorded concurrently wait for the snapshot and land after it.
+///
+/// [`take_snapshot()`](Self::take_snapshot) swaps every bucket with zero and the summary with
+/// an empty one under the locks, so each recorded value is counted in exactly one interval, in
+/// the buckets and in the summary alike.
+#[derive(Debug)]
+pub struct AtomicHistogram<const WIDTH: usize = 3> {
+    /// Log scale for value-to-bucket mapping.
+    log_scale: LogScale<WIDTH>,
+
+    /// Count of samples in each bucket.
+    buckets: Box<[AtomicU64]>,
+
+    /// Exact statistics of the recorded values, sharded by recording thread.
+    shards: Box<[Shard]>,
+}
+
+/// A summary shard, aligned so that no two shards share a cache line.
+#[derive(Debug, Default)]
+#[repr(align(128))]
+struct Shard(Mutex<Summary>);
+
+impl Shard {
+    fn lock(&self) -> MutexGuard<'_, Summary> {
+        // A panic cannot leave a summary half-updated: use it anyway.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<const WIDTH: usize> Default for AtomicHistogram<WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize> AtomicHistogram<WIDTH> {
+    /// Creates a new empty histogram with 252 buckets (for the default WIDTH=3).
+    pub fn new() -> Self {
+        Self::from_log_scale(LogScale::new())
+    }
+}
+
+impl<const WIDTH: usize> AtomicHistogram<WIDTH> {
+    /// Creates a new empty histogram with a custom log scale.
+    pub fn with_log_scale(log_scale: &'static LogScale<WIDTH>) -> Self {
+        Self::from_log_scale(*log_scale)
+    }
+
+    fn from_log_scale(log_scale: LogScale<WIDTH>) -> Self {
+        let num_shards = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(log_scale, num_shards.min(MAX_SHARDS))
+    }
+
+    fn with_shards(log_scale: LogScale<WIDTH>, num_shards: usize) -> Self {
+        let buckets = (0..log_scale.num_buckets()).map(|_| AtomicU64::new(0)).collect();
+        let shards = (0..num_shards).map(|_| Shard::default()).collect();
+        Self {
+            log_scale,
+            buckets,
+            shards,
+        }
+    }
+
+    /// Records a value.
+    #[inline]
+    pub fn record(&self, value: u64) {
+        let bucket_index = self.log_scale.calculate_bucket(value);
+        let mut summary = self.shard().lock();
+        self.buckets[bucket_index].fetch_add(1, Ordering::Relaxed);
+        summary.record(value);
+    }
+
+    /// Returns the total number of values recorded.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Returns a point-in-time copy of the bucket counts as a single-slot [`Histogram`].
+    ///
+    /// The min and max of the snapshot are kept within its non-empty buckets, see
+    /// [`take_snapshot()`](Self::take_snapshot).
+    pub fn snapshot<T>(&self) -> Histogram<T, WIDTH> {
+        let guards = self.lock_shards();
+        let buckets: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let mut summary = Summary::EMPTY;
+        for guard in guards {
+            summary.merge(&guard);
+        }
+        summary.clamp_to_buckets(&self.log_scale, &buckets);
+        Histogram::from_buckets(HistogramScale::Log(self.log_scale), buckets, summary)
+    }
+
+    /// Returns the values recorded so far as a snapshot, and resets the histogram to empty.
+    ///
+    /// Every bucket is swapped with zero, so recording can go on concurrently without losing
+    /// values, see [Consistency](Self#consistency). The summary always matches the buckets;
+    /// should its min and max still fall outside the non-empty buckets, or `min > max`, they
+    /// are recomputed from the bounds of the first and last non-empty bucket.
+    pub fn take_snapshot(&self) -> HistogramSnapshot<WIDTH> {
+        let guards = self.lock_shards();
+        let buckets: Vec<u64> = self.buckets.iter().map(|b| b.swap(0, Ordering::Relaxed)).collect();
+        let mut summary = Summary::EMPTY;
+        for mut guard in guards {
+            summary.merge(&mem::take(&mut *guard));
+        }
+        summary.clamp_to_buckets(&self.log_scale, &buckets);
+        HistogramSnapshot::new(
+            HistogramScale::Log(self.log_scale),
+            buckets,
+            summary,
+            vec![],
+        )
+    }
+
+    /// Returns common percentile statistics computed from a snapshot.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        self.snapshot::<()>().percentile_stats()
+    }
+
+    /// Returns the summary shard of the current thread.
+    #[inline]
+    fn shard(&self) -> &Shard {
+        // The thread-local is gone while the thread exits: any shard will do.
+        let index = SHARD.try_with(|&i| i).unwrap_or(0);
+        &self.shards[index % self.shards.len()]
+    }
+
+    /// Locks every shard, in order, so that no value is recorded until the guards are dropped.
+    fn lock_shards(&self) -> Vec<MutexGuard<'_, Summary>> {
+        self.shards.iter().map(Shard::lock).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_atomic_histogram_default() {
+        let hist: AtomicHistogram = AtomicHistogram::default();
+        assert_eq!(hist.total(), 0);
+        assert_eq!(hist.snapshot::<()>().total(), 0);
+    }
+
+    #[test]
+    fn test_record_matches_histogram() {
+        let atomic: AtomicHistogram = AtomicHistogram::new();
+        let mut plain: Histogram = Histogram::new();
+
+        for v in [0, 1, 7, 8, 42, 100, 4095, 4096, 1_000_000, u64::MAX] {
+            atomic.record(v);
+            plain.record(v);
+        }
+
+        let snapshot: Histogram = atomic.snapshot();
+        assert_eq!(snapshot, plain);
+        assert_eq!(atomic.total(), 10);
+        assert_eq!(atomic.percentile_stats(), plain.percentile_stats());
+    }
+
+    #[test]
+    fn test_concurrent_record() {
+        const NUM_THREADS: u64 = 8;
+        const PER_THREAD: u64 = 10_000;
+
+        let hist: AtomicHistogram = AtomicHistogram::new();
+
+        thread::scope(|s| {
+            for t in 0..NUM_THREADS {
+                let hist = &hist;
+                s.spawn(move || {
+                    for i in 0..PER_THREAD {
+                        hist.record(t * PER_THREAD + i);
+                    }
+                });
+            }
+        });
+
+        let mut expected: Histogram = Histogram::new();
+        for v in 0..NUM_THREADS * PER_THREAD {
+            expected.record(v);
+        }
+
+        assert_eq!(hist.total(), NUM_THREADS * PER_THREAD);
+        assert_eq!(hist.snapshot::<()>(), expected);
+    }
+
+    #[test]
+    fn test_sharded_summary() {
+        let hist: AtomicHistogram = AtomicHistogram::with_shards(LogScale::new(), 4);
+        let values: Vec<u64> = (0..8).flat_map(|t| [t, 100 + t, 1000 * t]).collect();
+
+        thread::scope(|s| {
+            for chunk in values.chunks(3) {
+                let hist = &hist;
+                s.spawn(move || {
+                    for &v in chunk {
+                        hist.record(v);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(hist.snapshot::<()>(), hist_of(&values));
+
+        let snapshot = hist.take_snapshot();
+        assert_eq!(snapshot.summary(), hist_of(&values).summary());
+        assert_eq!(hist.snapshot::<()>().summary(), Summary::EMPTY);
+    }
+
+    #[test]
+    fn test_width_4() {
+        let atomic: AtomicHistogram<4> = AtomicHistogram::new();
+        let mut plain: Histogram<(), 4> = Histogram::new();
+
+        for v in [3, 17, 100, 9999] {
+            atomic.record(v);
+            plain.record(v);
+        }
+
+        assert_eq!(atomic.snapshot::<()>(), plain);
+    }
+
+    #[test]
+    fn test_summary() {
+        let hist: AtomicHistogram = AtomicHistogram::new();
+        assert_eq!(hist.snapshot::<()>().summary(), Summary::EMPTY);
+
+        for v in [7, 3, 1000] {
+            hist.record(v);
+        }
+
+        let summary = hist.snapshot::<()>().summary();
+        assert_eq!(summary.min(), Some(3));
+        assert_eq!(summary.max(), Some(1000));
+        assert_eq!(summary.sum(), 1010);
+        assert_eq!(summary.sum_of_squares(), 49 + 9 + 1_000_000);
+    }
+
+    #[test]
+    fn test_take_snapshot() {
+        let hist: AtomicHistogram = AtomicHistogram::new();
+        for v in [7, 3, 1000] {
+            hist.record(v);
+        }
+
+        let snapshot = hist.take_snapshot();
+        let expected: Histogram = hist_of(&[7, 3, 1000]);
+        assert_eq!(snapshot.buckets(), expected.aggregate_buckets());
+        assert_eq!(snapshot.summary(), expected.summary());
+        assert_eq!(hist.total(), 0);
+        assert_eq!(hist.snapshot::<()>().summary(), Summary::EMPTY);
+
+        hist.record(42);
+        let next = hist.take_snapshot();
+        assert_eq!(next.summary(), hist_of(&[42]).summary());
+        assert_eq!(hist.take_snapshot().total(), 0);
+    }
+
+    #[test]
+    fn test_take_snapshot_concurrent() {
+        const NUM_THREADS: u64 = 4;
+        const PER_THREAD: u64 = 50_000;
+
+        let hist: AtomicHistogram = AtomicHistogram::new();
+        let mut intervals: Histogram = Histogram::new();
+        let mut checked = 0;
+
+        // Every snapshot taken while recording has its extremes within its buckets.
+        let check_extremes = |snapshot: &HistogramSnapshot| {
+            let (Some(min), Some(max)) = (snapshot.summary().min(), snapshot.summary().max())
+            else {
+                assert_eq!(snapshot.total(), 0);
+                return;
+            };
+            let first = snapshot.non_empty_buckets().next().unwrap();
+            let last = snapshot.non_empty_buckets().next_back().unwrap();
+            assert!(min <= max, "min {} > max {}", min, max);
+            assert!(first.lower_bound <= min && (max as u128) < last.upper_bound);
+        };
+
+        thread::scope(|s| {
+            let writers: Vec<_> = (0..NUM_THREADS)
+                .map(|t| {
+                    let hist = &hist;
+                    s.spawn(move || {
+                        for i in 0..PER_THREAD {
+                            hist.record(t * PER_THREAD + i);
+                        }
+                    })
+                })
+                .collect();
+
+            while writers.iter().any(|w| !w.is_finished()) {
+                check_extremes(&hist.snapshot::<()>().take_snapshot());
+                let snapshot = hist.take_snapshot();
+                check_extremes(&snapshot);
+                intervals.merge(&snapshot.into_histogram::<()>()).unwrap();
+                checked += 1;
+            }
+        });
+        intervals.merge(&hist.take_snapshot().into_histogram::<()>()).unwrap();
+
+        // No value is lost or counted twice across the intervals.
+        assert!(checked > 0);
+        let expected = hist_of(&(0..NUM_THREADS * PER_THREAD).collect::<Vec<_>>());
+        assert_eq!(intervals.aggregate_buckets(), expected.aggregate_buckets());
+        assert_eq!(intervals.summary().count(), expected.summary().count());
+        assert_eq!(intervals.summary().sum(), expected.summary().sum());
+        assert_eq!(
+            intervals.summary().sum_of_squares(),
+            expected.summary().sum_of_squares()
+        );
+    }
+
+    fn hist_of(values: &[u64]) -> Histogram {
+        let mut hist: Histogram = Histogram::new();
+        hist.record_slice(values);
+        hist
+    }
+
+    #[test]
+    fn test_snapshot_is_independent() {
+        let hist: AtomicHistogram = AtomicHistogram::new();
+        hist.record(10);
+
+        let snapshot: Histogram = hist.snapshot();
+        hist.record(20);
+
+        assert_eq!(snapshot.total(), 1);
+        assert_eq!(hist.total(), 2);
+    }
+}
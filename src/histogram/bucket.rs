@@ -0,0 +1,81 @@
+use super::bucket_scale::BucketScale;
+
+/// A non-empty bucket of a histogram: the number of values in `[lower_bound, upper_bound)`.
+///
+/// `upper_bound` is exclusive, hence a `u128`: the last bucket ends at `2^64`.
+///
+/// Returned by [`Histogram::non_empty_buckets()`](super::Histogram::non_empty_buckets) and the
+/// slot equivalents, to build custom exporters or visualizations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bucket {
+    /// Smallest value of the bucket, inclusive.
+    pub lower_bound: u64,
+    /// End of the bucket, exclusive.
+    pub upper_bound: u128,
+    /// Number of values recorded in the bucket.
+    pub count: u64,
+}
+
+/// Returns the non-empty `buckets` of `scale`, in ascending order of value.
+pub(crate) fn non_empty_buckets<'a>(
+    scale: &'a impl BucketScale,
+    buckets: &'a [u64],
+) -> impl DoubleEndedIterator<Item = Bucket> + 'a {
+    buckets
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(index, &count)| to_bucket(scale, index, count))
+}
+
+/// Returns the buckets of `scale` for `(index, count)` pairs of non-empty buckets.
+pub(crate) fn sparse_buckets<'a>(
+    scale: &'a impl BucketScale,
+    buckets: impl DoubleEndedIterator<Item = (usize, u64)> + 'a,
+) -> impl DoubleEndedIterator<Item = Bucket> + 'a {
+    buckets.map(|(index, count)| to_bucket(scale, index, count))
+}
+
+fn to_bucket(scale: &impl BucketScale, index: usize, count: u64) -> Bucket {
+    Bucket {
+        lower_bound: scale.bucket_min_value(index),
+        upper_bound: scale.bucket_max_value(index) as u128 + 1,
+        count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LOG_SCALE;
+
+    #[test]
+    fn test_non_empty_buckets() {
+        let mut buckets = vec![0; LOG_SCALE.num_buckets()];
+        buckets[LOG_SCALE.calculate_bucket(3)] = 2;
+        buckets[LOG_SCALE.calculate_bucket(100)] = 1;
+        buckets[LOG_SCALE.calculate_bucket(u64::MAX)] = 4;
+
+        let got: Vec<Bucket> = non_empty_buckets(&LOG_SCALE, &buckets).collect();
+        assert_eq!(got, vec![
+            Bucket {
+                lower_bound: 3,
+                upper_bound: 4,
+                count: 2,
+            },
+            Bucket {
+                lower_bound: 96,
+                upper_bound: 112,
+                count: 1,
+            },
+            Bucket {
+                lower_bound: 14 << 60,
+                upper_bound: 1 << 64,
+                count: 4,
+            },
+        ]);
+
+        assert_eq!(non_empty_buckets(&LOG_SCALE, &[0; 252]).count(), 0);
+    }
+}
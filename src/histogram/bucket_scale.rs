@@ -0,0 +1,163 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::log_scale::LogScale;
+
+/// Maps values to bucket indices and bucket indices back to value ranges.
+///
+/// Buckets are contiguous and in ascending order of value: bucket 0 starts at 0, each bucket
+/// starts one past the end of the previous one, and the last bucket ends at `u64::MAX`. Every
+/// value falls into exactly one bucket.
+///
+/// [`LogScale`] is the default; [`LinearScale`](super::LinearScale) and
+/// [`ExplicitBoundsScale`](super::ExplicitBoundsScale) suit metrics with a small or
+/// hand-picked set of buckets. Use [`Histogram::with_scale()`](super::Histogram::with_scale)
+/// to record into a custom scale.
+pub trait BucketScale: fmt::Debug + Send + Sync + 'static {
+    /// Returns the number of buckets.
+    fn num_buckets(&self) -> usize;
+
+    /// Returns the minimum value for the given bucket index.
+    fn bucket_min_value(&self, bucket: usize) -> u64;
+
+    /// Returns the maximum value (inclusive) for the given bucket index.
+    ///
+    /// This is one less than the minimum value of the next bucket, or `u64::MAX` for the last one.
+    fn bucket_max_value(&self, bucket: usize) -> u64 {
+        if bucket + 1 < self.num_buckets() {
+            self.bucket_min_value(bucket + 1) - 1
+        } else {
+            u64::MAX
+        }
+    }
+
+    /// Returns the index of the bucket containing `value`.
+    fn calculate_bucket(&self, value: u64) -> usize;
+}
+
+impl<const WIDTH: usize> BucketScale for LogScale<WIDTH> {
+    #[inline]
+    fn num_buckets(&self) -> usize {
+        LogScale::num_buckets(self)
+    }
+
+    #[inline]
+    fn bucket_min_value(&self, bucket: usize) -> u64 {
+        LogScale::bucket_min_value(self, bucket)
+    }
+
+    #[inline]
+    fn bucket_max_value(&self, bucket: usize) -> u64 {
+        LogScale::bucket_max_value(self, bucket)
+    }
+
+    #[inline]
+    fn calculate_bucket(&self, value: u64) -> usize {
+        LogScale::calculate_bucket(self, value)
+    }
+}
+
+/// The scale of a [`Histogram`](super::Histogram).
+///
+/// A [`LogScale`] is stored by value so that recording does not go through dynamic dispatch;
+/// any other scale is shared behind an `Arc`.
+#[derive(Clone)]
+pub(crate) enum HistogramScale<const WIDTH: usize> {
+    Log(LogScale<WIDTH>),
+    Custom(Arc<dyn BucketScale>),
+}
+
+impl<const WIDTH: usize> HistogramScale<WIDTH> {
+    /// Returns `true` if this is a log scale of the histogram's WIDTH.
+    pub(crate) fn is_log(&self) -> bool {
+        matches!(self, Self::Log(_))
+    }
+}
+
+impl<const WIDTH: usize> BucketScale for HistogramScale<WIDTH> {
+    #[inline]
+    fn num_buckets(&self) -> usize {
+        match self {
+            Self::Log(log_scale) => log_scale.num_buckets(),
+            Self::Custom(scale) => scale.num_buckets(),
+        }
+    }
+
+    #[inline]
+    fn bucket_min_value(&self, bucket: usize) -> u64 {
+        match self {
+            Self::Log(log_scale) => log_scale.bucket_min_value(bucket),
+            Self::Custom(scale) => scale.bucket_min_value(bucket),
+        }
+    }
+
+    #[inline]
+    fn bucket_max_value(&self, bucket: usize) -> u64 {
+        match self {
+            Self::Log(log_scale) => log_scale.bucket_max_value(bucket),
+            Self::Custom(scale) => scale.bucket_max_value(bucket),
+        }
+    }
+
+    #[inline]
+    fn calculate_bucket(&self, value: u64) -> usize {
+        match self {
+            Self::Log(log_scale) => log_scale.calculate_bucket(value),
+            Self::Custom(scale) => scale.calculate_bucket(value),
+        }
+    }
+}
+
+/// Two scales are equal if they have the same buckets, whatever their type.
+impl<const WIDTH: usize> PartialEq for HistogramScale<WIDTH> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Log(a), Self::Log(b)) => a == b,
+            (Self::Custom(a), Self::Custom(b)) if Arc::ptr_eq(a, b) => true,
+            _ => {
+                self.num_buckets() == other.num_buckets()
+                    && (0..self.num_buckets())
+                        .all(|b| self.bucket_min_value(b) == other.bucket_min_value(b))
+            }
+        }
+    }
+}
+
+impl<const WIDTH: usize> Eq for HistogramScale<WIDTH> {}
+
+impl<const WIDTH: usize> fmt::Debug for HistogramScale<WIDTH> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Log(log_scale) => log_scale.fmt(f),
+            Self::Custom(scale) => scale.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LinearScale;
+    use crate::histogram::LogScale3;
+
+    #[test]
+    fn test_histogram_scale_eq() {
+        let log = HistogramScale::<3>::Log(LogScale3::new());
+        let linear = HistogramScale::<3>::Custom(Arc::new(LinearScale::new(10, 4)));
+        assert_eq!(log, log.clone());
+        assert_eq!(linear, linear.clone());
+        assert_eq!(
+            linear,
+            HistogramScale::Custom(Arc::new(LinearScale::new(10, 4)))
+        );
+        assert_ne!(
+            linear,
+            HistogramScale::Custom(Arc::new(LinearScale::new(10, 5)))
+        );
+        assert_ne!(log, linear);
+
+        // A custom scale with the same buckets as the log scale is equal to it.
+        let copy = HistogramScale::<3>::Custom(Arc::new(LogScale3::new()));
+        assert_eq!(log, copy);
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A source of the current time for time-driven histograms.
+///
+/// Use [`SystemClock`] in production and [`ManualClock`] to drive time explicitly in tests.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// A [`Clock`] reading the monotonic system clock, [`Instant::now()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A [`Clock`] that only moves when [`advance()`](Self::advance) is called.
+///
+/// Clones share the same time, so a test can keep a clone to move the time of a histogram
+/// that owns another clone.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    /// Creates a clock starting at the current system time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManualClock").field("now", &self.now()).finish()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_shared_by_clones() {
+        let clock = ManualClock::new();
+        let cloned = clock.clone();
+        let start = clock.now();
+
+        assert_eq!(cloned.now(), start);
+
+        cloned.advance(Duration::from_secs(3));
+        assert_eq!(clock.now() - start, Duration::from_secs(3));
+        assert_eq!(cloned.now(), clock.now());
+    }
+
+    #[test]
+    fn test_system_clock_is_monotonic() {
+        let clock = SystemClock;
+        let a = clock.now();
+        let b = clock.now();
+        assert!(b >= a);
+    }
+}
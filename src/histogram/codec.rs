@@ -0,0 +1,575 @@
+//! Compact, versioned binary encoding for [`Histogram`].
+//!
+//! Layout of format version 1 (all integers are unsigned LEB128 varints unless noted):
+//!
+//! ```text
+//! version     u8      format version, currently 1
+//! width       u8      LogScale WIDTH the bucket indices refer to
+//! scale       u8      0 if the bucket indices refer to the log scale, 1 for a custom scale
+//! capacity            maximum number of slots
+//! slot_count          number of encoded slots, oldest first
+//! slot_count times:
+//!   non_zero          number of non-empty buckets in this slot
+//!   non_zero times:
+//!     index_delta     bucket index minus (previous index + 1); the first is the index itself
+//!     count           bucket count, never 0
+//!   if non_zero > 0:
+//!     min             smallest value in the slot
+//!     max             largest value in the slot
+//!     sum             sum of the values, up to u128
+//!     sum_of_squares  sum of the squared values, up to u128
+//! ```
+//!
+//! Only non-empty buckets are stored, so a slot with a handful of distinct values takes a few
+//! bytes instead of 2KB. The aggregate is not stored: it is rebuilt from the slots on decode.
+//! Slot metadata (`data: T`) is not part of the binary format; use the `serde` support to
+//! persist it. Exemplars are not persisted by either. Neither is a custom
+//! [`BucketScale`]: only the `scale` byte records that there is one, so that
+//! [`Histogram::decode()`] rejects a custom scale and [`Histogram::decode_with_scale()`] the log
+//! scale; the bucket indices refer to the scale passed to the latter.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::bucket_scale::BucketScale;
+use super::bucket_scale::HistogramScale;
+use super::histogram::Histogram;
+use super::log_scale::LogScale;
+use super::slot::Slot;
+use super::slot_buckets::SlotBuckets;
+use super::summary::Summary;
+
+/// The format version written by [`Histogram::encode()`].
+const VERSION: u8 = 1;
+
+/// Upper bound on the slot capacity accepted by [`Histogram::decode()`].
+///
+/// Protects against allocating huge slot buffers when decoding corrupted input.
+pub const MAX_DECODE_CAPACITY: usize = 1 << 16;
+
+/// Error returned when decoding a histogram fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended before a complete histogram was read.
+    UnexpectedEof,
+    /// The format version is not supported by this build.
+    UnsupportedVersion(u8),
+    /// The encoded WIDTH does not match the log scale of the target histogram.
+    WidthMismatch { expected: usize, actual: u8 },
+    /// The histogram was encoded with a custom scale: decode it with
+    /// [`Histogram::decode_with_scale()`].
+    CustomScale,
+    /// The histogram was encoded with the log scale: decode it with [`Histogram::decode()`].
+    LogScale,
+    /// The scale kind byte is neither log (0) nor custom (1).
+    InvalidScaleKind(u8),
+    /// A varint does not fit in its integer type.
+    VarintOverflow,
+    /// The capacity is 0 or larger than [`MAX_DECODE_CAPACITY`].
+    InvalidCapacity(u64),
+    /// The slot count is 0 or larger than the capacity.
+    InvalidSlotCount(u64),
+    /// A bucket index is out of range, or a bucket is stored with a zero count.
+    InvalidBucket,
+    /// A slot's min or max is not within its non-empty buckets.
+    InvalidSummary,
+    /// The aggregate count of a bucket overflows `u64`.
+    CountOverflow,
+    /// Extra bytes follow the encoded histogram.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of input"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            DecodeError::WidthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "log scale width mismatch: expected {}, got {}",
+                    expected, actual
+                )
+            }
+            DecodeError::CustomScale => {
+                write!(f, "histogram was encoded with a custom scale")
+            }
+            DecodeError::LogScale => write!(f, "histogram was encoded with the log scale"),
+            DecodeError::InvalidScaleKind(k) => write!(f, "invalid scale kind {}", k),
+            DecodeError::VarintOverflow => write!(f, "varint overflows its integer type"),
+            DecodeError::InvalidCapacity(c) => write!(f, "invalid slot capacity {}", c),
+            DecodeError::InvalidSlotCount(n) => write!(f, "invalid slot count {}", n),
+            DecodeError::InvalidBucket => write!(f, "invalid bucket index or count"),
+            DecodeError::InvalidSummary => write!(f, "slot min or max outside its buckets"),
+            DecodeError::CountOverflow => write!(f, "aggregate bucket count overflows u64"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after histogram"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl<T, const WIDTH: usize> Histogram<T, WIDTH> {
+    /// Encodes all active slots into a compact, versioned binary format.
+    ///
+    /// Only non-empty buckets are stored, as varint `(index delta, count)` pairs, so a sparse
+    /// slot takes a few bytes. The exact [`Summary`] of each slot is stored along. Slot metadata
+    /// (`data: T`) and exemplars are not encoded.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_header(
+            &mut buf,
+            WIDTH,
+            self.scale().is_log(),
+            self.capacity() as u64,
+            self.active_slot_count() as u64,
+        );
+        for slot in self.iter_slots() {
+            write_buckets(&mut buf, &slot.buckets.to_dense());
+            write_summary(&mut buf, &slot.summary);
+        }
+        buf
+    }
+
+    /// Encodes only the aggregate, as a single-slot histogram with capacity 1.
+    ///
+    /// This is the compact form to ship a combined view between nodes.
+    pub fn encode_aggregate(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_header(&mut buf, WIDTH, self.scale().is_log(), 1, 1);
+        write_buckets(&mut buf, self.aggregate_buckets());
+        write_summary(&mut buf, &self.summary());
+        buf
+    }
+
+    /// Decodes a histogram produced by [`encode()`](Self::encode) or
+    /// [`encode_aggregate()`](Self::encode_aggregate).
+    ///
+    /// The decoded slots have no metadata: `data` is `None` for every slot.
+    ///
+    /// Returns [`DecodeError::CustomScale`] for a histogram created with
+    /// [`with_scale()`](Self::with_scale).
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, HistogramScale::Log(LogScale::new()))
+    }
+
+    /// Decodes a histogram created with [`with_scale()`](Self::with_scale).
+    ///
+    /// The scale is not part of the encoding: `scale` must have the same buckets as the scale
+    /// of the encoded histogram. The WIDTH is not checked.
+    ///
+    /// Returns [`DecodeError::LogScale`] for a histogram created with the log scale.
+    pub fn decode_with_scale(bytes: &[u8], scale: impl BucketScale) -> Result<Self, DecodeError> {
+        Self::decode_with(bytes, HistogramScale::Custom(Arc::new(scale)))
+    }
+
+    fn decode_with(bytes: &[u8], scale: HistogramScale<WIDTH>) -> Result<Self, DecodeError> {
+        let mut r = Reader { bytes };
+
+        let version = r.read_u8()?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let width = r.read_u8()?;
+        let custom = match r.read_u8()? {
+            0 => false,
+            1 => true,
+            k => return Err(DecodeError::InvalidScaleKind(k)),
+        };
+        if !scale.is_log() {
+            if !custom {
+                return Err(DecodeError::LogScale);
+            }
+        } else {
+            if custom {
+                return Err(DecodeError::CustomScale);
+            }
+            if width as usize != WIDTH {
+                return Err(DecodeError::WidthMismatch {
+                    expected: WIDTH,
+                    actual: width,
+                });
+            }
+        }
+
+        let capacity = r.read_varint()?;
+        if capacity == 0 || capacity > MAX_DECODE_CAPACITY as u64 {
+            return Err(DecodeError::InvalidCapacity(capacity));
+        }
+
+        let slot_count = r.read_varint()?;
+        if slot_count == 0 || slot_count > capacity {
+            return Err(DecodeError::InvalidSlotCount(slot_count));
+        }
+
+        let num_buckets = scale.num_buckets();
+        let mut slots = Vec::with_capacity(slot_count as usize);
+        for _ in 0..slot_count {
+            let mut buckets = vec![0; num_buckets];
+            read_buckets(&mut r, &mut buckets)?;
+            let summary = read_summary(&mut r, &scale, &buckets)?;
+            slots.push(Slot::with_buckets(
+                SlotBuckets::from_dense(buckets),
+                summary,
+                None,
+            ));
+        }
+
+        if !r.bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+
+        Histogram::from_slots(scale, capacity as usize, slots).ok_or(DecodeError::CountOverflow)
+    }
+}
+
+fn write_header(buf: &mut Vec<u8>, width: usize, log: bool, capacity: u64, slot_count: u64) {
+    buf.push(VERSION);
+    buf.push(width as u8);
+    buf.push(if log { 0 } else { 1 });
+    write_varint(buf, capacity);
+    write_varint(buf, slot_count);
+}
+
+/// Writes the non-empty buckets as `(index_delta, count)` pairs, prefixed by their number.
+fn write_buckets(buf: &mut Vec<u8>, buckets: &[u64]) {
+    let non_zero = buckets.iter().filter(|&&c| c > 0).count();
+    write_varint(buf, non_zero as u64);
+
+    let mut next_index = 0;
+    for (index, &count) in buckets.iter().enumerate() {
+        if count > 0 {
+            write_varint(buf, (index - next_index) as u64);
+            write_varint(buf, count);
+            next_index = index + 1;
+        }
+    }
+}
+
+fn read_buckets(r: &mut Reader<'_>, buckets: &mut [u64]) -> Result<(), DecodeError> {
+    let non_zero = r.read_varint()?;
+
+    let mut next_index = 0u64;
+    for _ in 0..non_zero {
+        let index = next_index.checked_add(r.read_varint()?).ok_or(DecodeError::InvalidBucket)?;
+        let count = r.read_varint()?;
+        if index >= buckets.len() as u64 || count == 0 {
+            return Err(DecodeError::InvalidBucket);
+        }
+        buckets[index as usize] = count;
+        next_index = index + 1;
+    }
+    Ok(())
+}
+
+/// Writes the summary of a slot, unless the slot is empty.
+fn write_summary(buf: &mut Vec<u8>, summary: &Summary) {
+    if let (Some(min), Some(max)) = (summary.min(), summary.max()) {
+        write_varint(buf, min);
+        write_varint(buf, max);
+        write_varint_u128(buf, summary.sum());
+        write_varint_u128(buf, summary.sum_of_squares());
+    }
+}
+
+/// Reads the summary of a slot holding `buckets`, validating `min` and `max` against them.
+fn read_summary(
+    r: &mut Reader<'_>,
+    scale: &impl BucketScale,
+    buckets: &[u64],
+) -> Result<Summary, DecodeError> {
+    if buckets.iter().all(|&c| c == 0) {
+        return Ok(Summary::EMPTY);
+    }
+
+    let min = r.read_varint()?;
+    let max = r.read_varint()?;
+    let sum = r.read_varint_u128()?;
+    let sum_of_squares = r.read_varint_u128()?;
+
+    Summary::from_stored(scale, buckets, min, max, sum, sum_of_squares)
+        .ok_or(DecodeError::InvalidSummary)
+}
+
+fn write_varint(buf: &mut Vec<u8>, v: u64) {
+    write_varint_u128(buf, v as u128);
+}
+
+fn write_varint_u128(buf: &mut Vec<u8>, mut v: u128) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let (&b, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEof)?;
+        self.bytes = rest;
+        Ok(b)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, DecodeError> {
+        let v = self.read_varint_u128()?;
+        u64::try_from(v).map_err(|_| DecodeError::VarintOverflow)
+    }
+
+    fn read_varint_u128(&mut self) -> Result<u128, DecodeError> {
+        let mut v = 0u128;
+        for shift in (0..128).step_by(7) {
+            let b = self.read_u8()?;
+            let low = (b & 0x7f) as u128;
+            if shift == 126 && low > 3 {
+                return Err(DecodeError::VarintOverflow);
+            }
+            v |= low << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(DecodeError::VarintOverflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::ExplicitBoundsScale;
+    use crate::histogram::LinearScale;
+
+    #[test]
+    fn test_varint_round_trip() {
+        for v in [
+            0,
+            1,
+            127,
+            128,
+            300,
+            16_383,
+            16_384,
+            u32::MAX as u64,
+            u64::MAX,
+        ] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, v);
+            let mut r = Reader { bytes: &buf };
+            assert_eq!(r.read_varint(), Ok(v));
+            assert!(r.bytes.is_empty());
+        }
+
+        for v in [0, u64::MAX as u128 + 1, u128::MAX] {
+            let mut buf = Vec::new();
+            write_varint_u128(&mut buf, v);
+            let mut r = Reader { bytes: &buf };
+            assert_eq!(r.read_varint_u128(), Ok(v));
+            assert!(r.bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_varint_overflow() {
+        let mut r = Reader {
+            bytes: &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02],
+        };
+        assert_eq!(r.read_varint(), Err(DecodeError::VarintOverflow));
+
+        let mut r = Reader { bytes: &[0x80] };
+        assert_eq!(r.read_varint(), Err(DecodeError::UnexpectedEof));
+
+        let mut bytes = vec![0xff; 18];
+        bytes.push(0x04);
+        let mut r = Reader { bytes: &bytes };
+        assert_eq!(r.read_varint_u128(), Err(DecodeError::VarintOverflow));
+    }
+
+    #[test]
+    fn test_round_trip_empty() {
+        let hist: Histogram = Histogram::new();
+        let bytes = hist.encode();
+        assert_eq!(bytes, vec![1, 3, 0, 1, 1, 0]);
+        assert_eq!(Histogram::<()>::decode(&bytes).unwrap(), hist);
+    }
+
+    #[test]
+    fn test_round_trip_multi_slot() {
+        let mut hist: Histogram = Histogram::with_slots(4);
+        for v in [0, 1, 1, 42, 1000, u64::MAX] {
+            hist.record(v);
+        }
+        hist.advance(());
+        hist.advance(());
+        for v in 0..10_000 {
+            hist.record(v);
+        }
+
+        let decoded: Histogram<u64> = Histogram::decode(&hist.encode()).unwrap();
+        assert_eq!(decoded.capacity(), 4);
+        assert_eq!(decoded.active_slot_count(), 3);
+        assert_eq!(decoded.total(), hist.total());
+        assert_eq!(decoded.percentile_stats(), hist.percentile_stats());
+        assert_eq!(decoded.encode(), hist.encode());
+        assert_eq!(decoded.summary(), hist.summary());
+        for (a, b) in decoded.iter_slots().zip(hist.iter_slots()) {
+            assert_eq!(a.buckets, b.buckets);
+            assert_eq!(a.summary, b.summary);
+            assert_eq!(a.data, None);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_aggregate() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(3);
+        hist.record(5);
+        hist.advance(1);
+        hist.record(5);
+        hist.record(500);
+
+        let decoded: Histogram = Histogram::decode(&hist.encode_aggregate()).unwrap();
+        assert_eq!(decoded.capacity(), 1);
+        assert_eq!(decoded.active_slot_count(), 1);
+        assert_eq!(decoded.aggregate_buckets(), hist.aggregate_buckets());
+        assert_eq!(decoded.summary(), hist.summary());
+    }
+
+    #[test]
+    fn test_encoding_is_compact() {
+        let mut hist: Histogram = Histogram::new();
+        for _ in 0..1000 {
+            hist.record(100);
+        }
+        // header(5) + non_zero(1) + index(1) + count(2)
+        //   + min(1) + max(1) + sum(3) + sum_of_squares(4)
+        assert_eq!(hist.encode().len(), 18);
+    }
+
+    #[test]
+    fn test_round_trip_width_4() {
+        let mut hist: Histogram<(), 4> = Histogram::new();
+        for v in [1, 9, 100, 1000] {
+            hist.record(v);
+        }
+
+        let bytes = hist.encode();
+        assert_eq!(bytes[1], 4);
+        assert_eq!(Histogram::<(), 4>::decode(&bytes).unwrap(), hist);
+        assert_eq!(
+            Histogram::<()>::decode(&bytes).unwrap_err(),
+            DecodeError::WidthMismatch {
+                expected: 3,
+                actual: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_trip_custom_scale() {
+        let scale = ExplicitBoundsScale::new([10, 100]);
+        let mut hist: Histogram = Histogram::with_scale(scale.clone(), 2);
+        hist.record_slice(&[5, 50, 500, 5000]);
+
+        let bytes = hist.encode();
+        let decoded = Histogram::decode_with_scale(&bytes, scale).unwrap();
+        assert_eq!(decoded, hist);
+        assert_eq!(decoded.summary(), hist.summary());
+
+        // Its bucket indices are not log bucket indices.
+        assert_eq!(
+            Histogram::<()>::decode(&bytes).unwrap_err(),
+            DecodeError::CustomScale
+        );
+        assert_eq!(
+            Histogram::<()>::decode(&hist.encode_aggregate()).unwrap_err(),
+            DecodeError::CustomScale
+        );
+
+        // Log bucket indices are not its bucket indices either.
+        let mut log: Histogram = Histogram::new();
+        log.record(1000);
+        assert_eq!(
+            Histogram::<()>::decode_with_scale(&log.encode(), LinearScale::new(1, 300))
+                .unwrap_err(),
+            DecodeError::LogScale
+        );
+
+        // Bucket indices beyond the scale are rejected.
+        let mut wide: Histogram = Histogram::with_scale(LinearScale::new(1, 300), 1);
+        wide.record(200);
+        assert_eq!(
+            Histogram::<()>::decode_with_scale(&wide.encode(), LinearScale::new(1, 3)).unwrap_err(),
+            DecodeError::InvalidBucket
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let decode = |b: &[u8]| Histogram::<()>::decode(b).unwrap_err();
+
+        assert_eq!(decode(&[]), DecodeError::UnexpectedEof);
+        assert_eq!(
+            decode(&[2, 3, 0, 1, 1, 0]),
+            DecodeError::UnsupportedVersion(2)
+        );
+        assert_eq!(
+            decode(&[1, 3, 2, 1, 1, 0]),
+            DecodeError::InvalidScaleKind(2)
+        );
+        assert_eq!(decode(&[1, 4, 0, 1, 1, 0]), DecodeError::WidthMismatch {
+            expected: 3,
+            actual: 4
+        });
+        assert_eq!(decode(&[1, 3, 0, 0, 1, 0]), DecodeError::InvalidCapacity(0));
+        assert_eq!(
+            decode(&[1, 3, 0, 0x80, 0x80, 0x08, 1, 0]),
+            DecodeError::InvalidCapacity(1 << 17)
+        );
+        assert_eq!(decode(&[1, 3, 0, 1, 0]), DecodeError::InvalidSlotCount(0));
+        assert_eq!(
+            decode(&[1, 3, 0, 1, 2, 0, 0]),
+            DecodeError::InvalidSlotCount(2)
+        );
+        assert_eq!(
+            decode(&[1, 3, 0, 1, 1, 1, 0xfc, 0x01, 1]),
+            DecodeError::InvalidBucket
+        );
+        assert_eq!(
+            decode(&[1, 3, 0, 1, 1, 1, 0, 0]),
+            DecodeError::InvalidBucket
+        );
+        assert_eq!(decode(&[1, 3, 0, 1, 1, 1, 0]), DecodeError::UnexpectedEof);
+        assert_eq!(decode(&[1, 3, 0, 1, 1, 0, 0]), DecodeError::TrailingBytes);
+    }
+
+    #[test]
+    fn test_decode_invalid_summary() {
+        let decode = |b: &[u8]| Histogram::<()>::decode(b).unwrap_err();
+
+        // A slot holding one value in bucket 17, i.e. [40, 47].
+        let slot = |min: u8, max: u8| vec![1, 3, 0, 1, 1, 1, 17, 1, min, max, 42, 100];
+        assert!(Histogram::<()>::decode(&slot(42, 42)).is_ok());
+        assert_eq!(decode(&slot(39, 42)), DecodeError::InvalidSummary);
+        assert_eq!(decode(&slot(42, 48)), DecodeError::InvalidSummary);
+        assert_eq!(decode(&slot(43, 42)), DecodeError::InvalidSummary);
+        assert_eq!(decode(&slot(42, 42)[..10]), DecodeError::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_decode_count_overflow() {
+        let mut bytes = vec![1, 3, 0, 2, 2];
+        for _ in 0..2 {
+            // Bucket 0 holds u64::MAX zeros.
+            bytes.extend([1, 0]);
+            write_varint(&mut bytes, u64::MAX);
+            bytes.extend([0, 0, 0, 0]);
+        }
+        assert_eq!(
+            Histogram::<()>::decode(&bytes).unwrap_err(),
+            DecodeError::CountOverflow
+        );
+    }
+}
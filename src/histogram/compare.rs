@@ -0,0 +1,398 @@
+use std::fmt;
+
+use super::error::HistogramError;
+use super::histogram::Histogram;
+use super::percentile_mode::PercentileMode;
+use super::quantiles::bucket_quantiles;
+use super::quantiles::percentile_label;
+
+/// The difference between a baseline and a candidate distribution.
+///
+/// Returned by [`Histogram::compare()`], called on the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// The baseline and candidate values at each requested quantile, in request order.
+    pub quantiles: Vec<QuantileDelta>,
+    /// The Kolmogorov–Smirnov distance, see [`ks_distance()`](Histogram::ks_distance).
+    pub ks_distance: f64,
+}
+
+/// The baseline and candidate values at one quantile of a [`Comparison`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantileDelta {
+    /// The quantile, in `[0, 1]`.
+    pub quantile: f64,
+    /// The value at the quantile in the baseline.
+    pub baseline: u64,
+    /// The value at the quantile in the candidate.
+    pub candidate: u64,
+}
+
+impl QuantileDelta {
+    /// Returns `candidate / baseline`.
+    ///
+    /// Returns `1.0` if both are zero, and infinity if only the baseline is zero.
+    pub fn ratio(&self) -> f64 {
+        match (self.baseline, self.candidate) {
+            (0, 0) => 1.0,
+            (0, _) => f64::INFINITY,
+            (b, c) => c as f64 / b as f64,
+        }
+    }
+
+    /// Returns the relative change from baseline to candidate, e.g. `0.1` for a 10% increase.
+    pub fn change(&self) -> f64 {
+        self.ratio() - 1.0
+    }
+}
+
+impl Comparison {
+    /// Returns the delta at quantile `q`, if it was requested.
+    pub fn get(&self, q: f64) -> Option<&QuantileDelta> {
+        self.quantiles.iter().find(|d| d.quantile == q)
+    }
+}
+
+impl<T, const WIDTH: usize> Histogram<T, WIDTH> {
+    /// Compares the aggregate of `self`, the baseline, with that of `candidate` at each of the
+    /// quantiles `qs`, estimated according to `mode`.
+    ///
+    /// Returns [`HistogramError::ScaleMismatch`] if the histograms use scales with different
+    /// buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a quantile is not in `[0, 1]`.
+    pub fn compare<U>(
+        &self,
+        candidate: &Histogram<U, WIDTH>,
+        qs: &[f64],
+        mode: PercentileMode,
+    ) -> Result<Comparison, HistogramError> {
+        let ks_distance = self.ks_distance(candidate)?;
+
+        let baseline = bucket_quantiles(self.scale(), self.aggregate_buckets(), qs, mode);
+        let other = bucket_quantiles(candidate.scale(), candidate.aggregate_buckets(), qs, mode);
+        let quantiles = qs
+            .iter()
+            .zip(baseline.into_iter().zip(other))
+            .map(|(&quantile, (baseline, candidate))| QuantileDelta {
+                quantile,
+                baseline,
+                candidate,
+            })
+            .collect();
+
+        Ok(Comparison {
+            quantiles,
+            ks_distance,
+        })
+    }
+
+    /// Returns the Kolmogorov–Smirnov distance between the aggregates of `self` and `other`:
+    /// the largest difference between their cumulative distributions, in `[0, 1]`.
+    ///
+    /// The cumulative distributions are compared at bucket boundaries, so differences within a
+    /// bucket are not seen. Returns `0.0` if both histograms are empty and `1.0` if only one
+    /// is.
+    ///
+    /// Returns [`HistogramError::ScaleMismatch`] if the histograms use scales with different
+    /// buckets.
+    pub fn ks_distance<U>(&self, other: &Histogram<U, WIDTH>) -> Result<f64, HistogramError> {
+        if self.scale() != other.scale() {
+            return Err(HistogramError::ScaleMismatch);
+        }
+
+        let (a, b) = (self.aggregate_buckets(), other.aggregate_buckets());
+        let (total_a, total_b) = (self.total(), other.total());
+        match (total_a, total_b) {
+            (0, 0) => return Ok(0.0),
+            (0, _) | (_, 0) => return Ok(1.0),
+            _ => {}
+        }
+
+        let (mut cumulative_a, mut cumulative_b) = (0u64, 0u64);
+        let mut distance = 0.0f64;
+        for (&count_a, &count_b) in a.iter().zip(b.iter()) {
+            cumulative_a += count_a;
+            cumulative_b += count_b;
+            let cdf_a = cumulative_a as f64 / total_a as f64;
+            let cdf_b = cumulative_b as f64 / total_b as f64;
+            distance = distance.max((cdf_a - cdf_b).abs());
+        }
+        Ok(distance)
+    }
+}
+
+/// Thresholds deciding whether a candidate distribution regressed from a baseline.
+///
+/// ```
+/// # use databend_base::histogram::Histogram;
+/// # use databend_base::histogram::RegressionCheck;
+/// let mut baseline: Histogram = Histogram::new();
+/// let mut candidate: Histogram = Histogram::new();
+/// for v in 1..=1000 {
+///     baseline.record(v);
+///     candidate.record(v * 2);
+/// }
+///
+/// let verdict = RegressionCheck::new()
+///     .max_increase(0.99, 0.10)
+///     .check(&baseline, &candidate)
+///     .unwrap();
+/// assert!(verdict.is_regression());
+/// assert_eq!(verdict.to_string(), "regressed: P99 +100.0% (limit +10.0%)");
+/// ```
+///
+/// Percentiles are estimated with [`PercentileMode::Interpolate`] by default: with
+/// [`PercentileMode::LowerBound`], a shift smaller than a bucket is either invisible or
+/// reported as a whole bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegressionCheck {
+    /// `(quantile, max_increase)` pairs.
+    limits: Vec<(f64, f64)>,
+    max_ks_distance: Option<f64>,
+    mode: PercentileMode,
+}
+
+impl Default for RegressionCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegressionCheck {
+    /// Creates a check without thresholds, which never reports a regression.
+    pub fn new() -> Self {
+        Self {
+            limits: vec![],
+            max_ks_distance: None,
+            mode: PercentileMode::Interpolate,
+        }
+    }
+
+    /// Reports a regression if the value at `quantile` grows by more than `max_increase`, a
+    /// fraction of the baseline: `0.1` allows up to 10% more.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not in `[0, 1]`.
+    pub fn max_increase(mut self, quantile: f64, max_increase: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "quantile must be in [0, 1], got {}",
+            quantile
+        );
+        self.limits.push((quantile, max_increase));
+        self
+    }
+
+    /// Reports a regression if the Kolmogorov–Smirnov distance exceeds `max`, whichever the
+    /// direction of the change.
+    pub fn max_ks_distance(mut self, max: f64) -> Self {
+        self.max_ks_distance = Some(max);
+        self
+    }
+
+    /// Sets how percentiles are estimated within their buckets.
+    pub fn mode(mut self, mode: PercentileMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Compares `candidate` with `baseline` and returns which thresholds are exceeded.
+    ///
+    /// Returns [`HistogramError::ScaleMismatch`] if the histograms use scales with different
+    /// buckets.
+    pub fn check<T, U, const WIDTH: usize>(
+        &self,
+        baseline: &Histogram<T, WIDTH>,
+        candidate: &Histogram<U, WIDTH>,
+    ) -> Result<Verdict, HistogramError> {
+        let qs: Vec<f64> = self.limits.iter().map(|(q, _)| *q).collect();
+        let comparison = baseline.compare(candidate, &qs, self.mode)?;
+
+        let mut violations = vec![];
+        for (delta, &(_, limit)) in comparison.quantiles.iter().zip(&self.limits) {
+            if delta.change() > limit {
+                violations.push(Violation::Quantile {
+                    delta: *delta,
+                    limit,
+                });
+            }
+        }
+        if let Some(limit) = self.max_ks_distance
+            && comparison.ks_distance > limit
+        {
+            violations.push(Violation::KsDistance {
+                distance: comparison.ks_distance,
+                limit,
+            });
+        }
+
+        Ok(Verdict {
+            comparison,
+            violations,
+        })
+    }
+}
+
+/// The result of a [`RegressionCheck`].
+///
+/// Displays as `ok`, or as the list of exceeded thresholds, so it can be used as an assertion
+/// message: `assert!(!verdict.is_regression(), "{}", verdict)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    /// The comparison at the quantiles of the check's thresholds.
+    pub comparison: Comparison,
+    /// The thresholds that were exceeded, empty if none.
+    pub violations: Vec<Violation>,
+}
+
+impl Verdict {
+    /// Returns `true` if any threshold was exceeded.
+    pub fn is_regression(&self) -> bool {
+        !self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.violations.is_empty() {
+            return write!(f, "ok");
+        }
+        write!(f, "regressed: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// A threshold exceeded in a [`Verdict`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// The value at a quantile grew more than allowed.
+    Quantile { delta: QuantileDelta, limit: f64 },
+    /// The distributions are further apart than allowed.
+    KsDistance { distance: f64, limit: f64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Quantile { delta, limit } => write!(
+                f,
+                "{} {:+.1}% (limit {:+.1}%)",
+                percentile_label(delta.quantile),
+                delta.change() * 100.0,
+                limit * 100.0
+            ),
+            Violation::KsDistance { distance, limit } => {
+                write!(f, "KS distance {:.3} (limit {:.3})", distance, limit)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LinearScale;
+
+    fn histogram(values: impl IntoIterator<Item = u64>) -> Histogram {
+        let mut hist = Histogram::new();
+        hist.extend(values);
+        hist
+    }
+
+    #[test]
+    fn test_quantile_delta() {
+        let delta = |baseline, candidate| QuantileDelta {
+            quantile: 0.5,
+            baseline,
+            candidate,
+        };
+        assert_eq!(delta(100, 110).ratio(), 1.1);
+        assert!((delta(100, 110).change() - 0.1).abs() < 1e-12);
+        assert_eq!(delta(0, 0).ratio(), 1.0);
+        assert_eq!(delta(0, 5).ratio(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = histogram(1..=1000);
+        let candidate = histogram((1..=1000).map(|v| v + 500));
+
+        let c = baseline.compare(&candidate, &[0.5, 0.99], PercentileMode::LowerBound).unwrap();
+        assert_eq!(c.quantiles.len(), 2);
+        assert_eq!(
+            (c.quantiles[0].baseline, c.quantiles[0].candidate),
+            (448, 896)
+        );
+        assert_eq!(c.get(0.5).unwrap().ratio(), 2.0);
+        assert_eq!(c.get(0.9), None);
+        assert!(c.ks_distance > 0.4 && c.ks_distance < 0.6);
+    }
+
+    #[test]
+    fn test_ks_distance() {
+        let a = histogram(1..=100);
+        assert_eq!(a.ks_distance(&a).unwrap(), 0.0);
+
+        // Disjoint distributions.
+        let b = histogram(1000..=1100);
+        assert_eq!(a.ks_distance(&b).unwrap(), 1.0);
+        assert_eq!(b.ks_distance(&a).unwrap(), 1.0);
+
+        // Half of the values moved far up.
+        let c = histogram((1..=50).chain(1000..1050));
+        assert_eq!(a.ks_distance(&c).unwrap(), 0.5);
+
+        let empty = histogram([]);
+        assert_eq!(empty.ks_distance(&empty).unwrap(), 0.0);
+        assert_eq!(empty.ks_distance(&a).unwrap(), 1.0);
+
+        let linear: Histogram = Histogram::with_scale(LinearScale::new(10, 20), 1);
+        assert_eq!(a.ks_distance(&linear), Err(HistogramError::ScaleMismatch));
+        assert_eq!(
+            a.compare(&linear, &[0.5], PercentileMode::LowerBound).unwrap_err(),
+            HistogramError::ScaleMismatch
+        );
+    }
+
+    #[test]
+    fn test_regression_check() {
+        let baseline = histogram(1..=10_000);
+        let check = RegressionCheck::new().max_increase(0.5, 0.10).max_increase(0.99, 0.10);
+
+        // Same distribution.
+        let verdict = check.check(&baseline, &histogram(1..=10_000)).unwrap();
+        assert!(!verdict.is_regression(), "{}", verdict);
+        assert_eq!(verdict.to_string(), "ok");
+
+        // Only the tail is 50% slower.
+        let slower_tail = histogram((1..=10_000).map(|v| if v > 9_800 { v * 3 / 2 } else { v }));
+        let verdict = check.check(&baseline, &slower_tail).unwrap();
+        assert!(verdict.is_regression());
+        assert_eq!(verdict.violations.len(), 1);
+        let Violation::Quantile { delta, .. } = verdict.violations[0] else {
+            panic!("expected a quantile violation: {}", verdict);
+        };
+        assert_eq!(delta.quantile, 0.99);
+        assert!(verdict.to_string().starts_with("regressed: P99 +"));
+
+        // A faster candidate is not a regression, but the KS distance sees the change.
+        let faster = histogram((1..=10_000).map(|v| v / 2));
+        assert!(!check.check(&baseline, &faster).unwrap().is_regression());
+        let verdict = check.clone().max_ks_distance(0.2).check(&baseline, &faster).unwrap();
+        assert_eq!(verdict.violations.len(), 1);
+        assert!(matches!(
+            verdict.violations[0],
+            Violation::KsDistance { .. }
+        ));
+    }
+}
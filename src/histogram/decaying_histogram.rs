@@ -0,0 +1,283 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use super::clock::Clock;
+use super::clock::SystemClock;
+use super::log_scale::LogScale;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+
+/// Weights are rescaled once the newest weight would exceed `2^RENORMALIZE_EXPONENT`.
+///
+/// `f64` goes up to about `2^1023`: this leaves room for `2^64` records of the largest weight.
+const RENORMALIZE_EXPONENT: f64 = 64.0;
+
+/// A histogram whose values fade out exponentially with age, for percentiles that follow
+/// recent behavior without the steps of slot eviction.
+///
+/// A value recorded `age` ago weighs `2^(-age / half_life)`: it counts for 1 when recorded,
+/// for 1/2 after one half-life, for 1/4 after two. Percentiles are taken over the weighted
+/// values, using the same [`LogScale`] buckets as [`Histogram`](super::Histogram).
+///
+/// Uses forward decay: a value recorded at `t` is stored with the weight `2^((t - landmark) /
+/// half_life)`, which grows with time instead of decaying the stored weights on every tick.
+/// Ratios between weights, hence percentiles, do not change as time passes. When the newest
+/// weight gets too large, all weights are rescaled and the landmark moves to the current time.
+///
+/// Time is read from a [`Clock`], [`SystemClock`] by default; tests can use
+/// [`ManualClock`](super::ManualClock) to run deterministically.
+#[derive(Debug)]
+pub struct DecayingHistogram<C: Clock = SystemClock, const WIDTH: usize = 3> {
+    log_scale: LogScale<WIDTH>,
+    /// Forward-decayed weight of each bucket, relative to `landmark`.
+    weights: Vec<f64>,
+    clock: C,
+    half_life: Duration,
+    landmark: Instant,
+}
+
+impl<const WIDTH: usize> DecayingHistogram<SystemClock, WIDTH> {
+    /// Creates a histogram whose values lose half their weight every `half_life`, using the
+    /// system clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `half_life` is zero.
+    pub fn new(half_life: Duration) -> Self {
+        Self::with_clock(SystemClock, half_life)
+    }
+}
+
+impl<C: Clock, const WIDTH: usize> DecayingHistogram<C, WIDTH> {
+    /// Creates a histogram whose values lose half their weight every `half_life`, reading time
+    /// from `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `half_life` is zero.
+    pub fn with_clock(clock: C, half_life: Duration) -> Self {
+        assert!(!half_life.is_zero(), "half_life must be non-zero");
+
+        let log_scale = LogScale::<WIDTH>::new();
+        let landmark = clock.now();
+        Self {
+            weights: vec![0.0; log_scale.num_buckets()],
+            log_scale,
+            clock,
+            half_life,
+            landmark,
+        }
+    }
+}
+
+impl<C: Clock, const WIDTH: usize> DecayingHistogram<C, WIDTH> {
+    /// Records a value with a weight of 1 at the current time.
+    pub fn record(&mut self, value: u64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `count` occurrences of `value` at the current time.
+    pub fn record_n(&mut self, value: u64, count: u64) {
+        let mut exponent = self.exponent(self.clock.now());
+        if exponent > RENORMALIZE_EXPONENT {
+            self.renormalize();
+            exponent = 0.0;
+        }
+
+        let bucket_index = self.log_scale.calculate_bucket(value);
+        self.weights[bucket_index] += count as f64 * exponent.exp2();
+    }
+
+    /// Returns the decayed number of values: the sum of the current weights.
+    pub fn total(&self) -> f64 {
+        let sum: f64 = self.weights.iter().sum();
+        sum * (-self.exponent(self.clock.now())).exp2()
+    }
+
+    /// Calculates the value at the given percentile of the weighted values.
+    ///
+    /// Returns the minimum value of the bucket containing the percentile, or `0` if nothing
+    /// was recorded.
+    pub fn percentile(&self, p: f64) -> u64 {
+        self.percentile_with_mode(p, PercentileMode::LowerBound)
+    }
+
+    /// Calculates the value at the given percentile of the weighted values, estimated within
+    /// its bucket according to `mode`.
+    pub fn percentile_with_mode(&self, p: f64, mode: PercentileMode) -> u64 {
+        let total: f64 = self.weights.iter().sum();
+        self.weighted_percentile(total, p, mode)
+    }
+
+    /// Returns common percentile statistics of the weighted values.
+    ///
+    /// `samples` is the decayed number of values, [`total()`](Self::total), rounded.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        self.percentile_stats_with_mode(PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics, estimated within their buckets according to
+    /// `mode`.
+    pub fn percentile_stats_with_mode(&self, mode: PercentileMode) -> PercentileStats {
+        let total: f64 = self.weights.iter().sum();
+        let values: Vec<u64> = PercentileStats::QUANTILES
+            .iter()
+            .map(|&q| self.weighted_percentile(total, q, mode))
+            .collect();
+        PercentileStats::from_values(self.total().round() as u64, &values)
+    }
+
+    /// Returns the half-life of recorded values.
+    pub fn half_life(&self) -> Duration {
+        self.half_life
+    }
+
+    /// Returns the number of half-lives elapsed from the landmark to `now`.
+    fn exponent(&self, now: Instant) -> f64 {
+        now.saturating_duration_since(self.landmark).as_secs_f64() / self.half_life.as_secs_f64()
+    }
+
+    /// Rescales the weights relative to the current time, which becomes the landmark.
+    fn renormalize(&mut self) {
+        let now = self.clock.now();
+        let factor = (-self.exponent(now)).exp2();
+        for weight in &mut self.weights {
+            *weight *= factor;
+        }
+        self.landmark = now;
+    }
+
+    /// Finds the value at percentile `p` of the weights, which sum to `total`.
+    fn weighted_percentile(&self, total: f64, p: f64, mode: PercentileMode) -> u64 {
+        if total <= 0.0 {
+            return 0;
+        }
+
+        let target = total * p;
+        let mut cumulative = 0.0;
+        let mut last_non_empty = 0;
+        for (bucket_index, &weight) in self.weights.iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+            last_non_empty = bucket_index;
+            if cumulative + weight >= target {
+                let fraction = ((target - cumulative) / weight).clamp(0.0, 1.0);
+                return mode.estimate_weighted(
+                    self.log_scale.bucket_min_value(bucket_index),
+                    self.log_scale.bucket_max_value(bucket_index),
+                    fraction,
+                );
+            }
+            cumulative += weight;
+        }
+
+        // Rounding left the target above the sum of the weights.
+        mode.estimate_weighted(
+            self.log_scale.bucket_min_value(last_non_empty),
+            self.log_scale.bucket_max_value(last_non_empty),
+            1.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::ManualClock;
+
+    const HALF_LIFE: Duration = Duration::from_secs(10);
+
+    fn decaying(clock: &ManualClock) -> DecayingHistogram<ManualClock> {
+        DecayingHistogram::with_clock(clock.clone(), HALF_LIFE)
+    }
+
+    #[test]
+    fn test_total_decays() {
+        let clock = ManualClock::new();
+        let mut hist = decaying(&clock);
+        assert_eq!(hist.total(), 0.0);
+        assert_eq!(hist.percentile(0.5), 0);
+
+        hist.record_n(5, 4);
+        assert_eq!(hist.total(), 4.0);
+
+        clock.advance(HALF_LIFE);
+        assert_eq!(hist.total(), 2.0);
+        hist.record(5);
+        assert_eq!(hist.total(), 3.0);
+
+        clock.advance(HALF_LIFE * 2);
+        assert_eq!(hist.total(), 0.75);
+        assert_eq!(hist.half_life(), HALF_LIFE);
+    }
+
+    #[test]
+    fn test_percentile_shifts_gradually() {
+        let clock = ManualClock::new();
+        let mut hist = decaying(&clock);
+
+        // Old regime: 100 values at 10.
+        hist.record_n(10, 100);
+
+        // New regime: 100 values at 1000, one half-life later. The old values weigh 50.
+        clock.advance(HALF_LIFE);
+        hist.record_n(1000, 100);
+        assert_eq!(hist.percentile(0.3), 10);
+        assert_eq!(hist.percentile(0.4), 896);
+
+        // Two more half-lives: the old values weigh 12.5 against 25.
+        clock.advance(HALF_LIFE * 2);
+        assert_eq!(hist.percentile(0.3), 10);
+        // Fresh values weigh 100 more: the old values are 12.5 of 137.5, about 9%.
+        hist.record_n(1000, 100);
+        assert_eq!(hist.percentile(0.3), 896);
+        assert_eq!(hist.percentile(0.05), 10);
+    }
+
+    #[test]
+    fn test_percentile_stats() {
+        let clock = ManualClock::new();
+        let mut hist = decaying(&clock);
+        for v in 1..=1000 {
+            hist.record(v);
+        }
+
+        let mut exact = crate::histogram::Histogram::<()>::new();
+        for v in 1..=1000 {
+            exact.record(v);
+        }
+
+        // Values recorded at the same time weigh the same: percentiles match the plain
+        // histogram.
+        clock.advance(HALF_LIFE * 3);
+        let mut expected = exact.percentile_stats();
+        expected.samples = 125;
+        assert_eq!(hist.percentile_stats(), expected);
+    }
+
+    #[test]
+    fn test_renormalize() {
+        let clock = ManualClock::new();
+        let mut hist = decaying(&clock);
+        hist.record(10);
+
+        // Far beyond the point where 2^exponent overflows f64.
+        for _ in 0..100 {
+            clock.advance(HALF_LIFE * 50);
+            hist.record(1000);
+        }
+        assert!(hist.weights.iter().all(|w| w.is_finite()));
+        assert!((hist.total() - 1.0).abs() < 1e-9);
+        assert_eq!(hist.percentile(0.0), 896);
+
+        clock.advance(HALF_LIFE);
+        assert!((hist.total() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    #[should_panic(expected = "half_life must be non-zero")]
+    fn test_zero_half_life_panics() {
+        let _: DecayingHistogram = DecayingHistogram::new(Duration::ZERO);
+    }
+}
@@ -0,0 +1,25 @@
+use std::fmt;
+
+/// Error returned when combining histograms fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistogramError {
+    /// The two histograms use scales with different buckets, so their counts are not comparable.
+    ScaleMismatch,
+    /// Subtracting would make the count of a bucket negative.
+    Underflow { bucket: usize },
+}
+
+impl fmt::Display for HistogramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistogramError::ScaleMismatch => {
+                write!(f, "histograms use different scales")
+            }
+            HistogramError::Underflow { bucket } => {
+                write!(f, "histogram subtraction underflow at bucket {}", bucket)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistogramError {}
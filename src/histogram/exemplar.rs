@@ -0,0 +1,34 @@
+use std::time::SystemTime;
+
+/// A concrete recorded value with an identifier, e.g. the trace or query id of a request.
+///
+/// Recorded with [`Histogram::record_with_exemplar()`](super::Histogram::record_with_exemplar).
+/// A slot keeps one exemplar per bucket, the one with the latest timestamp, so that a spike in
+/// some percentile can be traced back to an example. Exemplars are evicted with their slot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Exemplar {
+    /// The exact recorded value.
+    pub value: u64,
+    /// The identifier of the example, such as a trace id.
+    pub id: String,
+    /// When the value was recorded.
+    pub timestamp: SystemTime,
+}
+
+impl Exemplar {
+    /// Creates an exemplar of `value` identified by `id`, timestamped now.
+    pub fn new(value: u64, id: impl ToString) -> Self {
+        Self {
+            value,
+            id: id.to_string(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// Sets the timestamp.
+    pub fn with_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+}
@@ -0,0 +1,78 @@
+use super::bucket_scale::BucketScale;
+
+/// A scale of hand-picked buckets, given by the value each bucket starts at.
+///
+/// Bucket `i` holds the values from its lower bound up to one less than the next lower bound;
+/// the last bucket holds every larger value. A bucket starting at 0 is added if the bounds do
+/// not include 0.
+///
+/// ```
+/// use databend_base::histogram::BucketScale;
+/// use databend_base::histogram::ExplicitBoundsScale;
+///
+/// // HTTP status classes: [0, 99], 1xx, 2xx, 3xx, 4xx, 5xx, [600, u64::MAX].
+/// let scale = ExplicitBoundsScale::new([100, 200, 300, 400, 500, 600]);
+/// assert_eq!(scale.num_buckets(), 7);
+/// assert_eq!(scale.calculate_bucket(404), 4);
+/// assert_eq!(scale.bucket_max_value(4), 499);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplicitBoundsScale {
+    /// Minimum value of each bucket, strictly ascending, starting at 0.
+    lower_bounds: Vec<u64>,
+}
+
+impl ExplicitBoundsScale {
+    /// Creates a scale with a bucket starting at each of `lower_bounds`.
+    ///
+    /// The bounds are sorted and deduplicated.
+    pub fn new(lower_bounds: impl IntoIterator<Item = u64>) -> Self {
+        let mut lower_bounds: Vec<u64> = lower_bounds.into_iter().collect();
+        lower_bounds.push(0);
+        lower_bounds.sort_unstable();
+        lower_bounds.dedup();
+        Self { lower_bounds }
+    }
+
+    /// Returns the minimum value of each bucket, in ascending order.
+    pub fn lower_bounds(&self) -> &[u64] {
+        &self.lower_bounds
+    }
+}
+
+impl BucketScale for ExplicitBoundsScale {
+    fn num_buckets(&self) -> usize {
+        self.lower_bounds.len()
+    }
+
+    fn bucket_min_value(&self, bucket: usize) -> u64 {
+        self.lower_bounds[bucket]
+    }
+
+    fn calculate_bucket(&self, value: u64) -> usize {
+        // `lower_bounds[0]` is 0, so at least one bound is at most `value`.
+        self.lower_bounds.partition_point(|&bound| bound <= value) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        let scale = ExplicitBoundsScale::new([50, 10, 0, 10]);
+        assert_eq!(scale.lower_bounds(), &[0, 10, 50]);
+        assert_eq!(scale.calculate_bucket(0), 0);
+        assert_eq!(scale.calculate_bucket(9), 0);
+        assert_eq!(scale.calculate_bucket(10), 1);
+        assert_eq!(scale.calculate_bucket(49), 1);
+        assert_eq!(scale.calculate_bucket(u64::MAX), 2);
+        assert_eq!(scale.bucket_max_value(1), 49);
+        assert_eq!(scale.bucket_max_value(2), u64::MAX);
+
+        let single = ExplicitBoundsScale::new([]);
+        assert_eq!(single.num_buckets(), 1);
+        assert_eq!(single.calculate_bucket(7), 0);
+    }
+}
@@ -0,0 +1,473 @@
+use super::bucket_scale::BucketScale;
+use super::histogram::Histogram;
+
+/// The smallest scale allowed by the OpenTelemetry exponential histogram data model.
+pub const MIN_EXPONENTIAL_SCALE: i32 = -10;
+
+/// The largest scale allowed by the OpenTelemetry exponential histogram data model.
+pub const MAX_EXPONENTIAL_SCALE: i32 = 20;
+
+/// The largest number of positive buckets produced by [`Histogram::to_exponential()`], the
+/// default of the OpenTelemetry SDKs.
+pub const MAX_EXPONENTIAL_BUCKETS: usize = 160;
+
+/// Exponential scale matching the default log scale, WIDTH=3.
+pub const DEFAULT_EXPONENTIAL_SCALE: i32 = ExponentialHistogram::scale_for_width(3);
+
+/// A histogram in the OpenTelemetry (OTLP) base-2 exponential histogram data model.
+///
+/// With `base = 2^(2^-scale)`, the positive bucket at index `i` counts values in
+/// `(base^i, base^(i+1)]`. `bucket_counts[k]` holds the count of index `offset + k`.
+/// Value 0 is counted in `zero_count`.
+///
+/// Convert a [`Histogram`] with [`Histogram::to_exponential()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExponentialHistogram {
+    /// Resolution of the buckets: each power of 2 is split into `2^scale` buckets.
+    pub scale: i32,
+    /// Total number of values, including `zero_count`.
+    pub count: u64,
+    /// Sum of the values.
+    pub sum: f64,
+    /// Smallest value, `None` if there is no value.
+    pub min: Option<f64>,
+    /// Largest value, `None` if there is no value.
+    pub max: Option<f64>,
+    /// Number of values equal to 0.
+    pub zero_count: u64,
+    /// Buckets for positive values.
+    pub positive: ExponentialBuckets,
+}
+
+/// A dense range of exponential histogram buckets starting at index `offset`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ExponentialBuckets {
+    /// Index of the first bucket in `bucket_counts`.
+    pub offset: i32,
+    /// Count of values in buckets `offset`, `offset + 1`, ...
+    pub bucket_counts: Vec<u64>,
+}
+
+impl ExponentialHistogram {
+    /// Returns the exponential scale matching `LogScale<WIDTH>`.
+    ///
+    /// A log scale of `WIDTH` splits each power of 2 into `2^(WIDTH - 1)` buckets, the same
+    /// number of buckets as exponential scale `WIDTH - 1`.
+    pub const fn scale_for_width(width: usize) -> i32 {
+        width as i32 - 1
+    }
+}
+
+impl<T, const WIDTH: usize> Histogram<T, WIDTH> {
+    /// Converts the aggregate to the OpenTelemetry exponential histogram data model, using at
+    /// most the scale matching this histogram's log scale.
+    ///
+    /// A histogram with a custom [`BucketScale`] has no matching scale: it uses at most the
+    /// scale matching the default log scale, [`DEFAULT_EXPONENTIAL_SCALE`].
+    ///
+    /// See [`to_exponential_with_scale()`](Self::to_exponential_with_scale).
+    pub fn to_exponential(&self) -> ExponentialHistogram {
+        let scale = if self.scale().is_log() {
+            ExponentialHistogram::scale_for_width(WIDTH)
+        } else {
+            DEFAULT_EXPONENTIAL_SCALE
+        };
+        self.to_exponential_with_scale(scale)
+    }
+
+    /// Converts the aggregate to the OpenTelemetry exponential histogram data model, using at
+    /// most the given `scale`.
+    ///
+    /// Each non-empty bucket is mapped as a whole to the exponential bucket containing its
+    /// upper bound. The scale used is the largest one, down to [`MIN_EXPONENTIAL_SCALE`], at
+    /// which:
+    ///
+    /// - no exponential boundary lies above the lower bound and below the upper bound of a bucket,
+    ///   so every value of the bucket but its lower bound is in the exponential bucket it is mapped
+    ///   to, and
+    /// - the positive buckets span at most [`MAX_EXPONENTIAL_BUCKETS`] indices.
+    ///
+    /// Exponential buckets include their upper bound and log buckets their lower bound, so the
+    /// lower bound of a log bucket may itself be an exponential boundary: a power of 2 at scale
+    /// 0 or below. Values equal to it then belong one exponential bucket below. Log buckets
+    /// holding a single value (values below `2^WIDTH`) are mapped exactly, so a histogram of
+    /// small values keeps `scale`; wider log buckets have boundaries at powers of 2 only, which
+    /// leads to scale 0 or below. A custom bucket holding 0 and positive values, such as
+    /// `[0, 9]`, is counted in full at its upper bound.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is not in
+    /// [`MIN_EXPONENTIAL_SCALE`]`..=`[`MAX_EXPONENTIAL_SCALE`].
+    pub fn to_exponential_with_scale(&self, scale: i32) -> ExponentialHistogram {
+        assert!(
+            (MIN_EXPONENTIAL_SCALE..=MAX_EXPONENTIAL_SCALE).contains(&scale),
+            "exponential scale must be in [{}, {}], got {}",
+            MIN_EXPONENTIAL_SCALE,
+            MAX_EXPONENTIAL_SCALE,
+            scale
+        );
+
+        let bucket_scale = self.scale();
+        let mut zero_count = 0;
+        // Bounds and count of each non-empty bucket holding positive values.
+        let mut buckets: Vec<(u64, u64, u64)> = vec![];
+
+        for (bucket, &count) in self.aggregate_buckets().iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let max = bucket_scale.bucket_max_value(bucket);
+            if max == 0 {
+                zero_count += count;
+            } else {
+                buckets.push((bucket_scale.bucket_min_value(bucket), max, count));
+            }
+        }
+
+        let mut scale = scale;
+        while scale > MIN_EXPONENTIAL_SCALE && !fits(&buckets, scale) {
+            scale -= 1;
+        }
+
+        let positive = match (buckets.first(), buckets.last()) {
+            (Some(&(_, first_max, _)), Some(&(_, last_max, _))) => {
+                let offset = value_to_index(first_max, scale);
+                let last = value_to_index(last_max, scale);
+                let mut bucket_counts = vec![0; (last - offset + 1) as usize];
+                for (_, max, count) in buckets {
+                    bucket_counts[(value_to_index(max, scale) - offset) as usize] += count;
+                }
+                ExponentialBuckets {
+                    offset,
+                    bucket_counts,
+                }
+            }
+            _ => ExponentialBuckets::default(),
+        };
+
+        let summary = self.summary();
+        ExponentialHistogram {
+            scale,
+            count: self.total(),
+            sum: summary.sum() as f64,
+            min: summary.min().map(|v| v as f64),
+            max: summary.max().map(|v| v as f64),
+            zero_count,
+            positive,
+        }
+    }
+}
+
+/// Returns `true` if, at `scale`, no exponential boundary lies strictly inside one of
+/// `buckets` and they span at most [`MAX_EXPONENTIAL_BUCKETS`] indices.
+///
+/// `buckets` holds the bounds and count of positive buckets, in ascending order.
+fn fits(buckets: &[(u64, u64, u64)], scale: i32) -> bool {
+    let (Some(&(_, first_max, _)), Some(&(_, last_max, _))) = (buckets.first(), buckets.last())
+    else {
+        return true;
+    };
+    let span = value_to_index(last_max, scale) - value_to_index(first_max, scale) + 1;
+    span as usize <= MAX_EXPONENTIAL_BUCKETS
+        && buckets.iter().all(|&(min, max, _)| {
+            min == max || value_to_index(min + 1, scale) == value_to_index(max, scale)
+        })
+}
+
+/// Returns the index of the exponential bucket `(base^i, base^(i+1)]` containing the positive
+/// integer `value`.
+///
+/// Values beyond `2^53` do not all convert to `f64` exactly. Unlike [`map_to_index()`] of the
+/// converted value, the rounding never moves them across a power of 2, and at scale 0 or below
+/// the index is computed on the integer alone.
+fn value_to_index(value: u64, scale: i32) -> i32 {
+    debug_assert!(value > 0);
+
+    // `value` is in `[2^exponent, 2^(exponent + 1))`.
+    let exponent = 63 - value.leading_zeros() as i32;
+    if value.is_power_of_two() {
+        // Upper bounds are inclusive: `2^exponent` belongs to the bucket below.
+        return ((exponent << scale.max(0)) - 1) >> (-scale).max(0);
+    }
+    if scale <= 0 {
+        return exponent >> -scale;
+    }
+    let first = exponent << scale;
+    let last = ((exponent + 1) << scale) - 1;
+    map_to_index(value as f64, scale).clamp(first, last)
+}
+
+/// Returns the index of the exponential bucket `(base^i, base^(i+1)]` containing `value`.
+///
+/// `value` must be positive and finite. Exact powers of 2 are handled without floating point
+/// error, as they lie on bucket boundaries for every scale.
+fn map_to_index(value: f64, scale: i32) -> i32 {
+    debug_assert!(value > 0.0 && value.is_finite());
+
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32 - 1023;
+    let is_power_of_two = bits & ((1u64 << 52) - 1) == 0;
+
+    if scale <= 0 {
+        // value is in (2^(exp), 2^(exp+1)], or exactly 2^exp which belongs to the bucket below.
+        let exp = if is_power_of_two {
+            exponent - 1
+        } else {
+            exponent
+        };
+        return exp >> -scale;
+    }
+
+    if is_power_of_two {
+        return (exponent << scale) - 1;
+    }
+
+    let scale_factor = (1u64 << scale) as f64;
+    (value.log2() * scale_factor).ceil() as i32 - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LOG_SCALE;
+    use crate::histogram::LinearScale;
+
+    /// Straightforward reference mapping, from the bucket definition `(base^i, base^(i+1)]`.
+    ///
+    /// Bucket bounds are computed as `2^(i / 2^scale)`, which is exact at powers of 2.
+    fn reference_index(value: f64, scale: i32) -> i32 {
+        let bound = |i: i32| (i as f64 * 2f64.powi(-scale)).exp2();
+
+        let mut i = (value.log2() * 2f64.powi(scale)).floor() as i32 - 2;
+        while bound(i + 1) < value {
+            i += 1;
+        }
+        // Upper bounds are inclusive: step back while `value` is not above the lower bound.
+        while bound(i) >= value {
+            i -= 1;
+        }
+        i
+    }
+
+    #[test]
+    fn test_scale_for_width() {
+        assert_eq!(ExponentialHistogram::scale_for_width(3), 2);
+        assert_eq!(ExponentialHistogram::scale_for_width(4), 3);
+    }
+
+    #[test]
+    fn test_map_to_index_powers_of_two() {
+        assert_eq!(map_to_index(1.0, 0), -1);
+        assert_eq!(map_to_index(2.0, 0), 0);
+        assert_eq!(map_to_index(3.0, 0), 1);
+        assert_eq!(map_to_index(4.0, 0), 1);
+        assert_eq!(map_to_index(4.0, 2), 7);
+        assert_eq!(map_to_index(5.0, 2), 9);
+        assert_eq!(map_to_index(1024.0, -1), 4);
+        assert_eq!(map_to_index(1025.0, -1), 5);
+    }
+
+    #[test]
+    fn test_map_to_index_matches_reference() {
+        for scale in [-4, -1, 0, 1, 2, 3, 5, 8] {
+            for bucket in 1..LOG_SCALE.num_buckets() {
+                let min = LOG_SCALE.bucket_min_value(bucket) as f64;
+                let max = LOG_SCALE.bucket_max_value(bucket) as f64;
+                for value in [min, (min + max) / 2.0, max] {
+                    assert_eq!(
+                        map_to_index(value, scale),
+                        reference_index(value, scale),
+                        "value={} scale={}",
+                        value,
+                        scale
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_exponential_empty() {
+        let hist: Histogram = Histogram::new();
+        assert_eq!(hist.to_exponential(), ExponentialHistogram {
+            scale: 2,
+            count: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+            zero_count: 0,
+            positive: ExponentialBuckets::default(),
+        });
+    }
+
+    #[test]
+    fn test_to_exponential_small_values_exact() {
+        let mut hist: Histogram = Histogram::new();
+        for v in [0, 0, 1, 2, 3, 4, 5, 6, 7] {
+            hist.record(v);
+        }
+
+        let exp = hist.to_exponential();
+        assert_eq!(exp.count, 9);
+        assert_eq!(exp.zero_count, 2);
+        assert_eq!(exp.sum, 28.0);
+        assert_eq!(exp.min, Some(0.0));
+        assert_eq!(exp.max, Some(7.0));
+
+        // Every value below 2^WIDTH is mapped to the bucket a reference implementation picks.
+        let mut expected = vec![0u64; 64];
+        for v in 1..=7 {
+            expected[(reference_index(v as f64, 2) - exp.positive.offset) as usize] += 1;
+        }
+        expected.truncate(exp.positive.bucket_counts.len());
+        assert_eq!(exp.positive.offset, reference_index(1.0, 2));
+        assert_eq!(exp.positive.bucket_counts, expected);
+    }
+
+    #[test]
+    fn test_to_exponential_counts_preserved() {
+        let mut hist: Histogram = Histogram::new();
+        for v in (0..100_000u64).map(|i| i * i) {
+            hist.record(v);
+        }
+
+        for scale in [-2, 0, 2, 4] {
+            let exp = hist.to_exponential_with_scale(scale);
+            let positive: u64 = exp.positive.bucket_counts.iter().sum();
+            assert_eq!(exp.zero_count + positive, hist.total());
+            assert_eq!(exp.count, hist.total());
+        }
+    }
+
+    #[test]
+    fn test_value_to_index_matches_reference() {
+        for scale in [-10, -3, -1, 0, 1, 2, 5, 20] {
+            let near_powers = (1..53).flat_map(|e| [(1 << e) - 1, 1 << e, (1 << e) + 1]);
+            for value in (1..5000).chain(near_powers) {
+                assert_eq!(
+                    value_to_index(value, scale),
+                    reference_index(value as f64, scale),
+                    "value={} scale={}",
+                    value,
+                    scale
+                );
+            }
+        }
+
+        // Beyond 2^53, values next to a power of 2 convert to it as `f64`.
+        for scale in [-1, 0, 3] {
+            let power = 1u64 << 60;
+            assert_eq!(
+                value_to_index(power + 1, scale),
+                value_to_index(power, scale) + 1
+            );
+            assert_eq!(
+                value_to_index(power - 1, scale),
+                value_to_index(power, scale)
+            );
+        }
+        assert_eq!(value_to_index(u64::MAX, 0), 63);
+        assert_eq!(value_to_index(u64::MAX, 2), 255);
+    }
+
+    #[test]
+    fn test_to_exponential_no_straddling() {
+        // Values spread over the range of `f64` integers, none a power of 2.
+        let mut values = vec![];
+        let mut v = 3u64;
+        while v < 1 << 53 {
+            values.push(v);
+            v = v * 3 / 2 + 1;
+        }
+        values.retain(|v| !v.is_power_of_two());
+
+        let mut hist: Histogram = Histogram::new();
+        hist.record_slice(&values);
+        let exp = hist.to_exponential();
+        assert!(exp.positive.bucket_counts.len() <= MAX_EXPONENTIAL_BUCKETS);
+
+        // No log bucket holds an exponential boundary above its lower bound.
+        for bucket in 1..LOG_SCALE.num_buckets() {
+            let min = LOG_SCALE.bucket_min_value(bucket);
+            let max = LOG_SCALE.bucket_max_value(bucket);
+            if max < 1 << 53 && min < max {
+                assert_eq!(
+                    reference_index((min + 1) as f64, exp.scale),
+                    reference_index(max as f64, exp.scale),
+                    "bucket [{}, {}] at scale {}",
+                    min,
+                    max,
+                    exp.scale
+                );
+            }
+        }
+
+        // So every value is counted in its own exponential bucket.
+        let mut expected = vec![0u64; exp.positive.bucket_counts.len()];
+        for &v in &values {
+            expected[(reference_index(v as f64, exp.scale) - exp.positive.offset) as usize] += 1;
+        }
+        assert_eq!(exp.positive.bucket_counts, expected);
+    }
+
+    #[test]
+    fn test_to_exponential_downscales_to_fit() {
+        // Single-value log buckets fit any scale: only the bucket count limits it.
+        let mut hist: Histogram = Histogram::new();
+        hist.record_slice(&[1, 7]);
+        let exp = hist.to_exponential_with_scale(MAX_EXPONENTIAL_SCALE);
+        assert_eq!(exp.scale, 5);
+        assert_eq!(exp.positive.offset, reference_index(1.0, 5));
+        assert_eq!(exp.positive.bucket_counts.len(), 91);
+        assert_eq!(exp.positive.bucket_counts.iter().sum::<u64>(), 2);
+
+        // The last log bucket, [7 * 2^61, u64::MAX], holds no boundary at scale 1, and the
+        // full range of `u64` takes 129 buckets.
+        hist.record(u64::MAX);
+        let exp = hist.to_exponential_with_scale(MAX_EXPONENTIAL_SCALE);
+        assert_eq!(exp.scale, 1);
+        assert_eq!(exp.positive.offset, -1);
+        assert_eq!(exp.positive.bucket_counts.len(), 129);
+    }
+
+    #[test]
+    fn test_to_exponential_width_4() {
+        let mut hist: Histogram<(), 4> = Histogram::new();
+        for v in 1..16 {
+            hist.record(v);
+        }
+
+        let exp = hist.to_exponential();
+        assert_eq!(exp.scale, 3);
+        assert_eq!(exp.positive.bucket_counts.iter().sum::<u64>(), 15);
+        for v in 1..16 {
+            let index = reference_index(v as f64, 3) - exp.positive.offset;
+            assert!(
+                exp.positive.bucket_counts[index as usize] > 0,
+                "value {}",
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_exponential_custom_scale() {
+        // WIDTH does not select the exponential scale of a custom scale.
+        let mut hist: Histogram<(), 4> = Histogram::with_scale(LinearScale::new(10, 4), 1);
+        hist.record_slice(&[0, 15, 15, 100]);
+
+        let exp = hist.to_exponential();
+        // [0, 9] holds 1, a boundary at every scale: no scale fits, the smallest is used.
+        assert_eq!(exp.scale, MIN_EXPONENTIAL_SCALE);
+        // 0 shares the bucket [0, 9], which is mapped as a whole.
+        assert_eq!(exp.zero_count, 0);
+        assert_eq!(exp.positive.bucket_counts, vec![4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "exponential scale must be in [-10, 20], got 21")]
+    fn test_to_exponential_invalid_scale() {
+        Histogram::<()>::new().to_exponential_with_scale(21);
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+
+use super::bucket_scale::BucketScale;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+use super::quantiles::Quantiles;
+use super::signed_histogram::SignedHistogram;
+
+/// A histogram of `f64` values, e.g. ratios, with the same relative precision at any magnitude.
+///
+/// Values are bucketed by their binary exponent and the `WIDTH - 1` high bits of their mantissa,
+/// the way a [`LogScale`](super::LogScale) buckets `u64` values by their most significant bits.
+/// Every bucket of normal values is therefore at most `2^(1 - WIDTH)` wide relative to its
+/// lower bound, 25% for the default WIDTH=3, from `f64::MIN_POSITIVE` up to `f64::MAX`.
+/// Subnormal values share a few coarser buckets, and zero has its own bucket.
+///
+/// Negative and positive values are kept apart as in a [`SignedHistogram`], and percentiles
+/// are taken in signed order. Min and max are exact.
+///
+/// NaN and infinities are not recorded: they are counted by [`rejected()`](Self::rejected).
+///
+/// The buckets span the whole `f64` range: each side has `2^(WIDTH - 1) * 2047 + 1`
+/// buckets, 8189 for WIDTH=3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatHistogram<const WIDTH: usize = 3> {
+    /// Histogram of the bit patterns of the values, see [`FloatScale`].
+    inner: SignedHistogram<WIDTH>,
+    /// Sum of the values in each slot, oldest first.
+    sums: VecDeque<f64>,
+    rejected: u64,
+}
+
+impl<const WIDTH: usize> Default for FloatHistogram<WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize> FloatHistogram<WIDTH> {
+    /// Creates a new float histogram with 1 slot.
+    pub fn new() -> Self {
+        Self::with_slots(1)
+    }
+
+    /// Creates a new float histogram with the specified slot capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_slots(capacity: usize) -> Self {
+        let mut sums = VecDeque::with_capacity(capacity);
+        sums.push_back(0.0);
+        Self {
+            inner: SignedHistogram::with_scale(FloatScale::<WIDTH>, capacity),
+            sums,
+            rejected: 0,
+        }
+    }
+}
+
+impl<const WIDTH: usize> FloatHistogram<WIDTH> {
+    /// Records a value to the current (last) slot, or counts it as rejected if it is NaN or
+    /// infinite.
+    pub fn record(&mut self, value: f64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `count` occurrences of `value` to the current (last) slot, or counts them as
+    /// rejected if `value` is NaN or infinite.
+    pub fn record_n(&mut self, value: f64, count: u64) {
+        if !value.is_finite() {
+            self.rejected += count;
+            return;
+        }
+        self.inner.record_n(to_key(value), count);
+        *self.sums.back_mut().unwrap() += value * count as f64;
+    }
+
+    /// Advances to a new slot, evicting the oldest if at capacity.
+    pub fn advance(&mut self) {
+        self.inner.advance();
+        if self.sums.len() == self.inner.positive().capacity() {
+            self.sums.pop_front();
+        }
+        self.sums.push_back(0.0);
+    }
+
+    /// Adds all values of `other` to the current slot.
+    pub fn merge(&mut self, other: &FloatHistogram<WIDTH>) {
+        self.inner.merge(&other.inner);
+        *self.sums.back_mut().unwrap() += other.sums.iter().sum::<f64>();
+        self.rejected += other.rejected;
+    }
+
+    /// Returns the number of values not recorded because they were NaN or infinite. Never
+    /// reset by slot eviction.
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Returns the total number of values recorded across all slots, not counting rejected
+    /// values.
+    pub fn total(&self) -> u64 {
+        self.inner.total()
+    }
+
+    /// Returns the smallest recorded value, or `None` if there is no value.
+    pub fn min(&self) -> Option<f64> {
+        self.inner.min().map(from_key)
+    }
+
+    /// Returns the largest recorded value, or `None` if there is no value.
+    pub fn max(&self) -> Option<f64> {
+        self.inner.max().map(from_key)
+    }
+
+    /// Returns the arithmetic mean of the recorded values, or `None` if there is no value.
+    pub fn mean(&self) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| self.sums.iter().sum::<f64>() / total as f64)
+    }
+
+    /// Calculates the value at the given percentile. See [`SignedHistogram::percentile()`].
+    pub fn percentile(&self, p: f64) -> f64 {
+        from_key(self.inner.percentile(p))
+    }
+
+    /// Calculates the value at the given percentile, estimated within its bucket according to
+    /// `mode`.
+    pub fn percentile_with_mode(&self, p: f64, mode: PercentileMode) -> f64 {
+        from_key(self.inner.percentile_with_mode(p, mode))
+    }
+
+    /// Returns common percentile statistics: samples, P0.1, P1, P5, P10, P50, P90, P99, P99.9.
+    pub fn percentile_stats(&self) -> PercentileStats<f64> {
+        self.percentile_stats_with_mode(PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics, estimated within their buckets according to
+    /// `mode`.
+    pub fn percentile_stats_with_mode(&self, mode: PercentileMode) -> PercentileStats<f64> {
+        let values: Vec<f64> = self
+            .inner
+            .values_at(&PercentileStats::QUANTILES, mode)
+            .into_iter()
+            .map(from_key)
+            .collect();
+        PercentileStats::from_values(self.total(), &values)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, in the order of `qs`.
+    ///
+    /// Values are estimated like [`percentile()`](Self::percentile). The result also holds
+    /// the exact min and max.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a quantile is not in `[0, 1]`.
+    pub fn quantiles(&self, qs: &[f64]) -> Quantiles<f64> {
+        self.quantiles_with_mode(qs, PercentileMode::LowerBound)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, estimated within their buckets
+    /// according to `mode`. See [`quantiles()`](Self::quantiles).
+    pub fn quantiles_with_mode(&self, qs: &[f64], mode: PercentileMode) -> Quantiles<f64> {
+        let values = self.inner.values_at(qs, mode).into_iter().map(from_key);
+        Quantiles::from_values(self.total(), qs, values, self.min(), self.max())
+    }
+}
+
+/// Returns the key of a finite `value`: the bit pattern of its magnitude, with its sign.
+///
+/// The bit patterns of non-negative floats are in the same order as their values, and those
+/// of finite floats are below `2^63`.
+fn to_key(value: f64) -> i64 {
+    let magnitude = value.abs().to_bits() as i64;
+    if value < 0.0 { -magnitude } else { magnitude }
+}
+
+/// Returns the value of a key, or of an estimate within the bucket of a key.
+///
+/// An estimate in the last bucket may be beyond the largest finite float: it is clamped to it.
+fn from_key(key: i64) -> f64 {
+    let magnitude = f64::from_bits(key.unsigned_abs().min(f64::MAX.to_bits()));
+    if key < 0 { -magnitude } else { magnitude }
+}
+
+/// Buckets the keys of non-negative floats, see [`to_key()`], by exponent and the `WIDTH - 1`
+/// high bits of the mantissa.
+///
+/// Bucket 0 holds only zero. Bucket `i > 0` holds the keys whose bits above the low
+/// [`SHIFT`](Self::SHIFT) are `i - 1`: all of them share an exponent, so the bucket spans a
+/// fixed fraction of its lower bound.
+#[derive(Debug, Clone, Copy)]
+struct FloatScale<const WIDTH: usize>;
+
+impl<const WIDTH: usize> FloatScale<WIDTH> {
+    /// Number of low bits of the mantissa not used for bucketing.
+    const SHIFT: u32 = f64::MANTISSA_DIGITS - WIDTH as u32;
+}
+
+impl<const WIDTH: usize> BucketScale for FloatScale<WIDTH> {
+    fn num_buckets(&self) -> usize {
+        (f64::MAX.to_bits() >> Self::SHIFT) as usize + 2
+    }
+
+    fn bucket_min_value(&self, bucket: usize) -> u64 {
+        match bucket {
+            0 => 0,
+            _ => (((bucket - 1) as u64) << Self::SHIFT).max(1),
+        }
+    }
+
+    fn calculate_bucket(&self, value: u64) -> usize {
+        match value {
+            0 => 0,
+            // Keys beyond the largest finite float are never recorded, but still need a bucket.
+            _ => ((value >> Self::SHIFT) as usize + 1).min(self.num_buckets() - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the largest float below `value`.
+    fn below(value: f64) -> f64 {
+        f64::from_bits(value.to_bits() - 1)
+    }
+
+    #[test]
+    fn test_record_and_percentile() {
+        let mut hist: FloatHistogram = FloatHistogram::new();
+        assert_eq!(hist.percentile(0.5), 0.0);
+        assert_eq!((hist.min(), hist.max(), hist.mean()), (None, None, None));
+
+        for v in [-0.5, 0.0, 0.0004, 0.25, 1.5] {
+            hist.record(v);
+        }
+        assert_eq!(hist.total(), 5);
+        assert_eq!(hist.rejected(), 0);
+        assert_eq!(hist.min(), Some(-0.5));
+        assert_eq!(hist.max(), Some(1.5));
+        assert_eq!(hist.mean(), Some((-0.5 + 0.0 + 0.0004 + 0.25 + 1.5) / 5.0));
+
+        // -0.5 is in the bucket [-0.625, -0.5]: the lower bound is just above -0.625.
+        assert_eq!(hist.percentile(0.0), -below(0.625));
+        assert_eq!(hist.percentile(0.4), 0.0);
+        let p60 = hist.percentile(0.6);
+        assert!(p60 <= 0.0004 && 0.0004 < p60 * 1.25);
+        assert_eq!(hist.percentile(0.8), 0.25);
+        let quantiles = hist.quantiles(&[1.0]);
+        assert_eq!(quantiles.get(1.0), Some(1.5));
+        assert_eq!(
+            (quantiles.samples, quantiles.min, quantiles.max),
+            (5, Some(-0.5), Some(1.5))
+        );
+        let stats = hist.percentile_stats();
+        assert_eq!(
+            (stats.samples, stats.p0_1, stats.p50, stats.p99_9),
+            (5, -below(0.625), p60, 1.5)
+        );
+        assert_eq!(
+            hist.percentile_with_mode(1.0, PercentileMode::UpperBound),
+            below(1.75)
+        );
+    }
+
+    #[test]
+    fn test_uniform_relative_error() {
+        let values = [
+            1e-300, 3.3e-100, 2.0e-9, 0.7, 1.0, 123.456, 9.9e12, 4.2e150, 1e300,
+        ];
+        for v in values.into_iter().flat_map(|v| [v, -v]) {
+            let mut hist: FloatHistogram = FloatHistogram::new();
+            hist.record(v);
+
+            let lower = hist.percentile_with_mode(0.5, PercentileMode::LowerBound);
+            let upper = hist.percentile_with_mode(0.5, PercentileMode::UpperBound);
+            assert!(
+                lower <= v && v <= upper,
+                "{} not in [{}, {}]",
+                v,
+                lower,
+                upper
+            );
+            assert!(
+                (upper - lower).abs() <= 0.25 * v.abs(),
+                "bucket [{}, {}] of {} too wide",
+                lower,
+                upper,
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_finite() {
+        let mut hist: FloatHistogram = FloatHistogram::new();
+        for v in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            hist.record(v);
+        }
+        hist.record_n(f64::NAN, 2);
+        assert_eq!(hist.rejected(), 5);
+        assert_eq!(hist.total(), 0);
+
+        for v in [f64::MAX, -f64::MAX, 5e-324, -0.0] {
+            hist.record(v);
+        }
+        assert_eq!(hist.total(), 4);
+        assert_eq!(hist.min(), Some(-f64::MAX));
+        assert_eq!(hist.max(), Some(f64::MAX));
+        assert_eq!(hist.percentile(0.0), -f64::MAX);
+        assert_eq!(hist.percentile(0.5), 0.0);
+        assert_eq!(hist.percentile(0.75), 5e-324);
+        assert_eq!(
+            hist.percentile_with_mode(1.0, PercentileMode::UpperBound),
+            f64::MAX
+        );
+
+        let mut other: FloatHistogram = FloatHistogram::new();
+        other.record(f64::NAN);
+        hist.merge(&other);
+        assert_eq!(hist.rejected(), 6);
+    }
+
+    #[test]
+    fn test_merge_and_advance() {
+        let mut a: FloatHistogram = FloatHistogram::with_slots(2);
+        let mut b: FloatHistogram = FloatHistogram::new();
+        a.record(1.0);
+        b.record(2.0);
+        a.merge(&b);
+        assert_eq!(a.total(), 2);
+        assert_eq!(a.mean(), Some(1.5));
+
+        a.advance();
+        a.record(6.0);
+        assert_eq!(a.mean(), Some(3.0));
+
+        // The first slot, with 1.0 and 2.0, is evicted.
+        a.advance();
+        assert_eq!((a.total(), a.mean()), (1, Some(6.0)));
+        assert_eq!(a.min(), Some(6.0));
+    }
+
+    #[test]
+    fn test_float_scale() {
+        let scale = FloatScale::<3>;
+        assert_eq!(scale.num_buckets(), 8189);
+        assert_eq!(scale.calculate_bucket(0), 0);
+        assert_eq!(scale.bucket_max_value(0), 0);
+        assert_eq!(scale.bucket_min_value(1), 1);
+        assert_eq!(scale.calculate_bucket(u64::MAX), 8188);
+
+        // [1, 2) is split into 4 buckets of 0.25.
+        let one = scale.calculate_bucket(1f64.to_bits());
+        for (i, v) in [1.0, 1.25, 1.5, 1.75, 2.0].into_iter().enumerate() {
+            assert_eq!(scale.bucket_min_value(one + i), f64::to_bits(v));
+        }
+    }
+}
@@ -1,14 +1,46 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::collections::VecDeque;
-
-use super::log_scale::LOG_SCALE;
-use super::log_scale::LogScale3;
+use std::mem::size_of;
+use std::ops::AddAssign;
+use std::ops::Bound;
+use std::ops::Range;
+use std::ops::RangeBounds;
+use std::ops::SubAssign;
+use std::sync::Arc;
+
+use super::bucket::Bucket;
+use super::bucket::non_empty_buckets;
+use super::bucket_scale::BucketScale;
+use super::bucket_scale::HistogramScale;
+use super::error::HistogramError;
+use super::exemplar::Exemplar;
+use super::histogram_snapshot::HistogramSnapshot;
+use super::log_scale::LogScale;
+use super::percentile_mode::PercentileMode;
 use super::percentile_stats::PercentileStats;
+use super::quantiles::Quantiles;
+use super::quantiles::bucket_quantiles;
+use super::slot_buckets::SlotBuckets;
+use super::slot_snapshot::SlotSnapshot;
+use super::slot_view::SlotView;
+use super::summary::Summary;
 use crate::histogram::slot::Slot;
 
 /// A histogram for tracking the distribution of u64 values using logarithmic bucketing.
 ///
-/// This histogram provides O(1) recording and efficient percentile calculation with
-/// bounded memory usage (252 buckets = ~2KB per slot), regardless of the number of samples.
+/// This histogram provides fast recording and efficient percentile calculation with
+/// bounded memory usage (252 buckets = ~2KB per slot at most), regardless of the number of
+/// samples.
+///
+/// `WIDTH` selects the bucket precision, see [`LogScaleConfig`](super::LogScaleConfig).
+/// The default WIDTH=3 is described below; e.g. `Histogram<(), 4>` uses 496 buckets with
+/// ~6.25% max error. Every width shares the same API; widths 1 through 8 use lookup tables
+/// built at compile time (see [`SharedLogScale`](super::SharedLogScale)).
+///
+/// Other bucketings, e.g. [`LinearScale`](super::LinearScale), are used with
+/// [`with_scale()`](Self::with_scale); `WIDTH` is then unused. Recording into the log scale
+/// does not go through dynamic dispatch.
 ///
 /// # Multi-Slot Support
 ///
@@ -56,12 +88,14 @@ use crate::histogram::slot::Slot;
 ///
 /// # Memory Usage
 ///
-/// Fixed at 252 buckets * 8 bytes = 2,016 bytes per slot, covering the entire
-/// u64 range [0, 2^64-1].
+/// The aggregate takes 252 buckets * 8 bytes = 2,016 bytes, covering the entire u64 range
+/// [0, 2^64-1]. A slot takes as much at most: with several slots, each stores only its
+/// non-empty buckets while few are used, see [`memory_usage()`](Self::memory_usage).
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Histogram<T = ()> {
-    /// Log scale for value-to-bucket mapping.
-    log_scale: &'static LogScale3,
+pub struct Histogram<T = (), const WIDTH: usize = 3> {
+    /// Scale for value-to-bucket mapping: a log scale, a copy of references to static tables,
+    /// or a shared custom scale.
+    scale: HistogramScale<WIDTH>,
 
     /// Slots containing bucket counts and metadata. Uses VecDeque for O(1) front removal.
     /// All slots in the deque are active. First slot (index 0) is oldest, last is current.
@@ -71,18 +105,24 @@ pub struct Histogram<T = ()> {
     /// Aggregate bucket counts across all active slots.
     /// Maintained incrementally: +1 on record(), -slot on eviction.
     aggregate_buckets: Vec<u64>,
+
+    /// Exact statistics across all active slots but the current one, so that recording only
+    /// updates the current slot's summary. The current slot is merged in on advance() and by
+    /// [`summary()`](Self::summary); min and max are recomputed from slots on eviction.
+    previous_summary: Summary,
 }
 
-impl<T> Default for Histogram<T> {
+impl<T, const WIDTH: usize> Default for Histogram<T, WIDTH> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<T> Histogram<T> {
-    /// Creates a new histogram with 1 slot and 252 buckets.
+impl<T, const WIDTH: usize> Histogram<T, WIDTH> {
+    /// Creates a new histogram with 1 slot and 252 buckets (for the default WIDTH=3).
     ///
-    /// Memory usage: 252 * 8 bytes = 2,016 bytes per histogram.
+    /// The aggregate takes 252 * 8 bytes = 2,016 bytes, and so does the slot, see
+    /// [`memory_usage()`](Self::memory_usage).
     pub fn new() -> Self {
         Self::with_slots(1)
     }
@@ -97,9 +137,11 @@ impl<T> Histogram<T> {
     ///
     /// Panics if `capacity` is 0.
     pub fn with_slots(capacity: usize) -> Self {
-        Self::with_log_scale(&LOG_SCALE, capacity)
+        Self::with_histogram_scale(HistogramScale::Log(LogScale::new()), capacity)
     }
+}
 
+impl<T, const WIDTH: usize> Histogram<T, WIDTH> {
     /// Creates a new histogram with custom log scale and slot capacity.
     ///
     /// # Arguments
@@ -110,49 +152,361 @@ impl<T> Histogram<T> {
     /// # Panics
     ///
     /// Panics if `capacity` is 0.
-    pub fn with_log_scale(log_scale: &'static LogScale3, capacity: usize) -> Self {
+    pub fn with_log_scale(log_scale: &'static LogScale<WIDTH>, capacity: usize) -> Self {
+        Self::with_histogram_scale(HistogramScale::Log(*log_scale), capacity)
+    }
+
+    /// Creates a new histogram with a custom bucket scale and slot capacity.
+    ///
+    /// Histograms can only be merged or compared if their scales have the same buckets.
+    ///
+    /// `WIDTH` has no meaning with a custom scale: it only selects the log scale, and is left
+    /// in the type so that both kinds of histograms share one type. Leave it to the default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_scale(scale: impl BucketScale, capacity: usize) -> Self {
+        Self::with_histogram_scale(HistogramScale::Custom(Arc::new(scale)), capacity)
+    }
+
+    fn with_histogram_scale(scale: HistogramScale<WIDTH>, capacity: usize) -> Self {
         assert!(capacity > 0, "capacity must be at least 1");
 
-        let num_buckets = log_scale.num_buckets();
+        let num_buckets = scale.num_buckets();
 
         let mut slots = VecDeque::with_capacity(capacity);
-        slots.push_back(Slot::new(num_buckets));
+        slots.push_back(Self::empty_slot(num_buckets, capacity));
 
         Self {
-            log_scale,
+            scale,
             slots,
             aggregate_buckets: vec![0; num_buckets],
+            previous_summary: Summary::EMPTY,
+        }
+    }
+
+    /// Creates a single-slot histogram whose only slot holds the given bucket counts and
+    /// summary.
+    ///
+    /// `buckets` must have exactly `scale.num_buckets()` entries.
+    pub(crate) fn from_buckets(
+        scale: HistogramScale<WIDTH>,
+        buckets: Vec<u64>,
+        summary: Summary,
+    ) -> Self {
+        debug_assert_eq!(buckets.len(), scale.num_buckets());
+
+        let mut slots = VecDeque::with_capacity(1);
+        slots.push_back(Slot::with_buckets(
+            SlotBuckets::dense(buckets.clone()),
+            summary,
+            None,
+        ));
+
+        Self {
+            scale,
+            slots,
+            aggregate_buckets: buckets,
+            previous_summary: Summary::EMPTY,
+        }
+    }
+
+    /// Creates a histogram from already populated slots, rebuilding the aggregate.
+    ///
+    /// Returns `None` if a bucket count in the aggregate would overflow `u64`.
+    /// `slots` must be non-empty, at most `capacity` long, and each slot must have exactly
+    /// `scale.num_buckets()` buckets.
+    pub(crate) fn from_slots(
+        scale: HistogramScale<WIDTH>,
+        capacity: usize,
+        mut slots: Vec<Slot<T>>,
+    ) -> Option<Self> {
+        debug_assert!(!slots.is_empty() && slots.len() <= capacity);
+
+        // See `empty_slot()`.
+        if capacity == 1 {
+            slots[0].buckets.densify();
+        }
+
+        let mut aggregate_buckets = vec![0u64; scale.num_buckets()];
+        let mut previous_summary = Summary::EMPTY;
+        for slot in &slots {
+            for (index, count) in slot.buckets.iter() {
+                aggregate_buckets[index] = aggregate_buckets[index].checked_add(count)?;
+            }
+        }
+        for slot in &slots[..slots.len() - 1] {
+            previous_summary.merge(&slot.summary);
+        }
+
+        let mut deque = VecDeque::with_capacity(capacity);
+        deque.extend(slots);
+
+        Some(Self {
+            scale,
+            slots: deque,
+            aggregate_buckets,
+            previous_summary,
+        })
+    }
+
+    /// Creates an empty slot for a histogram of `capacity` slots.
+    ///
+    /// Sparse storage makes recording slower, and the only slot of a single-slot histogram
+    /// saves little next to the dense aggregate: it is dense from the start.
+    fn empty_slot(num_buckets: usize, capacity: usize) -> Slot<T> {
+        if capacity == 1 {
+            Slot::with_buckets(
+                SlotBuckets::dense(vec![0; num_buckets]),
+                Summary::EMPTY,
+                None,
+            )
+        } else {
+            Slot::new(num_buckets)
         }
     }
 
     /// Records a value to the current (last) slot.
     pub fn record(&mut self, value: u64) {
-        let bucket_index = self.log_scale.calculate_bucket(value);
-        self.slots.back_mut().unwrap().buckets[bucket_index] += 1;
+        let bucket_index = self.scale.calculate_bucket(value);
+        self.record_in_bucket(bucket_index, value);
+    }
+
+    /// Records `value`, which falls into bucket `bucket_index`, and returns the current slot.
+    #[inline]
+    fn record_in_bucket(&mut self, bucket_index: usize, value: u64) -> &mut Slot<T> {
+        let current = self.slots.back_mut().unwrap();
+        current.buckets.add(bucket_index, 1);
+        current.summary.record(value);
         self.aggregate_buckets[bucket_index] += 1;
+        current
+    }
+
+    /// Records `count` occurrences of `value` to the current (last) slot.
+    ///
+    /// Equivalent to calling [`record()`](Self::record) `count` times, for callers that
+    /// pre-aggregate values. Does nothing if `count` is 0.
+    pub fn record_n(&mut self, value: u64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let bucket_index = self.scale.calculate_bucket(value);
+        let current = self.slots.back_mut().unwrap();
+        current.buckets.add(bucket_index, count);
+        current.summary.record_n(value, count);
+        self.aggregate_buckets[bucket_index] += count;
+    }
+
+    /// Records the value of `exemplar` to the current (last) slot, and keeps `exemplar` as the
+    /// example of its bucket in that slot.
+    ///
+    /// A bucket keeps the exemplar with the latest timestamp. Exemplars are evicted with their
+    /// slot, and are listed by [`exemplars()`](Self::exemplars) and the exporters.
+    pub fn record_with_exemplar(&mut self, exemplar: Exemplar) {
+        let bucket_index = self.scale.calculate_bucket(exemplar.value);
+        self.record_in_bucket(bucket_index, exemplar.value).offer_exemplar(bucket_index, exemplar);
+    }
+
+    /// Records all values of `values` to the current (last) slot.
+    ///
+    /// Faster than calling [`record()`](Self::record) in a loop: the current slot is looked up
+    /// once, and the summary statistics are accumulated locally and merged at the end.
+    pub fn record_iter(&mut self, values: impl IntoIterator<Item = u64>) {
+        let current = self.slots.back_mut().unwrap();
+        let mut summary = Summary::EMPTY;
+        for value in values {
+            let bucket_index = self.scale.calculate_bucket(value);
+            current.buckets.add(bucket_index, 1);
+            self.aggregate_buckets[bucket_index] += 1;
+            summary.record(value);
+        }
+        current.summary.merge(&summary);
+    }
+
+    /// Records all values of a slice to the current (last) slot.
+    ///
+    /// See [`record_iter()`](Self::record_iter).
+    pub fn record_slice(&mut self, values: &[u64]) {
+        self.record_iter(values.iter().copied());
     }
 
     /// Advances to a new slot, evicting the oldest if at capacity.
     ///
-    /// Returns the number of active slots after advancing.
+    /// Returns the number of active slots after advancing. Use
+    /// [`advance_and_take()`](Self::advance_and_take) to get the evicted slot.
+    pub fn advance(&mut self, data: T) -> usize {
+        self.advance_and_take(data);
+        self.slots.len()
+    }
+
+    /// Advances to a new slot like [`advance()`](Self::advance), and returns the evicted slot,
+    /// or `None` if the histogram was not full.
     ///
     /// Logic:
-    /// 1. If at capacity, remove the oldest slot (front)
-    /// 2. Push a new slot to the back with the given data
-    pub fn advance(&mut self, data: T) -> usize {
+    /// 1. Add the current slot's summary to the summary of the previous slots
+    /// 2. If at capacity, remove the oldest slot (front)
+    /// 3. Push a new slot to the back with the given data
+    pub fn advance_and_take(&mut self, data: T) -> Option<SlotSnapshot<T, WIDTH>> {
+        // The current slot becomes a previous one
+        let current = self.slots.back().unwrap();
+        self.previous_summary.merge(&current.summary);
+
+        let mut evicted = None;
         if self.slots.len() == self.slots.capacity() {
             // Subtract evicted slot from aggregate
-            let evicted = self.slots.pop_front().unwrap();
-            for (i, &count) in evicted.buckets.iter().enumerate() {
+            let slot = self.slots.pop_front().unwrap();
+            for (i, count) in slot.buckets.iter() {
                 self.aggregate_buckets[i] -= count;
             }
+            self.previous_summary.subtract_totals(&slot.summary);
+            self.refresh_previous_extremes();
+            evicted = Some(SlotSnapshot::new(self.scale.clone(), slot));
         }
 
-        let mut slot = Slot::new(self.log_scale.num_buckets());
+        let mut slot = Self::empty_slot(self.scale.num_buckets(), self.slots.capacity());
         slot.data = Some(data);
         self.slots.push_back(slot);
 
-        self.slots.len()
+        evicted
+    }
+
+    /// Removes all values, leaving a single empty slot without metadata, like a new histogram
+    /// with the same scale and capacity.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.slots.push_back(Self::empty_slot(
+            self.scale.num_buckets(),
+            self.slots.capacity(),
+        ));
+        self.aggregate_buckets.fill(0);
+        self.previous_summary = Summary::EMPTY;
+    }
+
+    /// Returns the aggregate of all slots as a snapshot, and [`clear()`](Self::clear)s the
+    /// histogram to start a new interval.
+    ///
+    /// For periodic reporting: the snapshot holds everything recorded since the previous call.
+    pub fn take_snapshot(&mut self) -> HistogramSnapshot<WIDTH> {
+        let snapshot = HistogramSnapshot::new(
+            self.scale.clone(),
+            self.aggregate_buckets.clone(),
+            self.summary(),
+            self.exemplars().into_iter().cloned().collect(),
+        );
+        self.clear();
+        snapshot
+    }
+
+    /// Adds all samples of `other` to the current slot.
+    ///
+    /// Only the aggregate of `other` is merged; its slot layout and metadata are ignored.
+    /// Use this to combine per-worker or per-node histograms into a single view.
+    ///
+    /// Returns [`HistogramError::ScaleMismatch`] if the histograms use scales with different
+    /// buckets.
+    pub fn merge<U>(&mut self, other: &Histogram<U, WIDTH>) -> Result<(), HistogramError> {
+        self.check_scale(other)?;
+
+        let current = self.slots.back_mut().unwrap();
+        for (i, &count) in other.aggregate_buckets.iter().enumerate() {
+            if count > 0 {
+                current.buckets.add(i, count);
+                self.aggregate_buckets[i] += count;
+            }
+        }
+        current.summary.merge(&other.summary());
+        for (index, exemplar) in other.bucket_exemplars() {
+            current.offer_exemplar(index, exemplar.clone());
+        }
+        Ok(())
+    }
+
+    /// Removes all samples of `other` from the current slot.
+    ///
+    /// The exact min and max of the remaining samples are unknown: they are narrowed to the
+    /// non-empty buckets, see [`Summary`].
+    ///
+    /// Returns [`HistogramError::Underflow`] without modifying `self` if the current slot does
+    /// not contain enough samples in some bucket, or [`HistogramError::ScaleMismatch`] if the
+    /// histograms use scales with different buckets.
+    pub fn subtract<U>(&mut self, other: &Histogram<U, WIDTH>) -> Result<(), HistogramError> {
+        self.check_scale(other)?;
+
+        let current = self.slots.back_mut().unwrap();
+        let underflow = other
+            .aggregate_buckets
+            .iter()
+            .enumerate()
+            .position(|(i, &b)| current.buckets.get(i) < b);
+        if let Some(bucket) = underflow {
+            return Err(HistogramError::Underflow { bucket });
+        }
+
+        for (i, &count) in other.aggregate_buckets.iter().enumerate() {
+            if count > 0 {
+                current.buckets.sub(i, count);
+                self.aggregate_buckets[i] -= count;
+            }
+        }
+        current.summary.subtract_totals(&other.summary());
+        current.summary.clamp_to_buckets(&self.scale, &current.buckets.to_dense());
+        current.retain_exemplars();
+        Ok(())
+    }
+
+    /// Returns a new single-slot histogram holding the samples in `self` that are not in
+    /// `other`, comparing aggregates.
+    ///
+    /// Typically used to compute "what changed since the last scrape" from two snapshots of the
+    /// same histogram, where `other` is the older one. The min and max of the result are
+    /// narrowed to its non-empty buckets, see [`Summary`].
+    ///
+    /// Returns [`HistogramError::Underflow`] if `other` has more samples than `self` in some
+    /// bucket, or [`HistogramError::ScaleMismatch`] if the histograms use scales with different
+    /// buckets.
+    pub fn difference<U>(
+        &self,
+        other: &Histogram<U, WIDTH>,
+    ) -> Result<Histogram<T, WIDTH>, HistogramError> {
+        self.check_scale(other)?;
+
+        let buckets = self
+            .aggregate_buckets
+            .iter()
+            .zip(other.aggregate_buckets.iter())
+            .enumerate()
+            .map(|(bucket, (&a, &b))| a.checked_sub(b).ok_or(HistogramError::Underflow { bucket }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut summary = self.summary();
+        summary.subtract_totals(&other.summary());
+        summary.clamp_to_buckets(&self.scale, &buckets);
+
+        Ok(Histogram::from_buckets(
+            self.scale.clone(),
+            buckets,
+            summary,
+        ))
+    }
+
+    /// Recomputes the min and max of the previous slots from `slots`, while the new current
+    /// slot is not pushed yet.
+    fn refresh_previous_extremes(&mut self) {
+        let (min, max) = self.slots.iter().map(|slot| slot.summary.extremes()).fold(
+            Summary::EMPTY.extremes(),
+            |(min, max), (slot_min, slot_max)| (min.min(slot_min), max.max(slot_max)),
+        );
+        self.previous_summary.set_extremes(min, max);
+    }
+
+    /// Returns an error if `other` does not use a scale with the same buckets as `self`.
+    fn check_scale<U>(&self, other: &Histogram<U, WIDTH>) -> Result<(), HistogramError> {
+        if self.scale == other.scale {
+            Ok(())
+        } else {
+            Err(HistogramError::ScaleMismatch)
+        }
     }
 
     /// Returns the number of active slots.
@@ -161,6 +515,24 @@ impl<T> Histogram<T> {
         self.slots.len()
     }
 
+    /// Returns the approximate number of bytes used by this histogram, including heap
+    /// allocations, exemplars included, but excluding those owned by slot metadata `T`.
+    ///
+    /// The aggregate always stores all buckets, 8 bytes each, and so does the slot of a
+    /// single-slot histogram. With several slots, a slot stores only its non-empty buckets, 12
+    /// bytes each, until more than a quarter of its buckets are used, and all buckets from then
+    /// on.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.aggregate_buckets.capacity() * size_of::<u64>()
+            + self.slots.capacity() * size_of::<Slot<T>>()
+            + self
+                .slots
+                .iter()
+                .map(|slot| slot.buckets.heap_size() + slot.exemplars_heap_size())
+                .sum::<usize>()
+    }
+
     /// Returns the slot capacity.
     #[inline]
     pub fn capacity(&self) -> usize {
@@ -184,6 +556,24 @@ impl<T> Histogram<T> {
         self.slots.back().unwrap()
     }
 
+    /// Returns the scale used for value-to-bucket mapping.
+    #[inline]
+    pub(crate) fn scale(&self) -> &HistogramScale<WIDTH> {
+        &self.scale
+    }
+
+    /// Returns an iterator over active slots, from oldest to newest.
+    #[inline]
+    pub(crate) fn iter_slots(&self) -> impl Iterator<Item = &Slot<T>> {
+        self.slots.iter()
+    }
+
+    /// Returns the aggregate bucket counts across all active slots.
+    #[inline]
+    pub(crate) fn aggregate_buckets(&self) -> &[u64] {
+        &self.aggregate_buckets
+    }
+
     /// Returns the total number of values recorded across all slots.
     pub fn total(&self) -> u64 {
         self.aggregate_buckets.iter().sum()
@@ -196,46 +586,260 @@ impl<T> Histogram<T> {
     ///
     /// Returns `0` if the histogram is empty.
     pub fn percentile(&self, p: f64) -> u64 {
+        self.percentile_with_mode(p, PercentileMode::LowerBound)
+    }
+
+    /// Calculates the value at the given percentile, estimated within its bucket according to
+    /// `mode`.
+    ///
+    /// See [`PercentileMode`] for the error bounds of each mode. Returns `0` if the histogram is
+    /// empty.
+    pub fn percentile_with_mode(&self, p: f64, mode: PercentileMode) -> u64 {
         let total = self.total();
-        self.percentile_with_total(p, total)
+        bucket_percentile(&self.scale, &self.aggregate_buckets, total, p, mode)
     }
 
-    /// Calculates the percentile given a specific total count.
+    /// Returns common percentile statistics: samples, P0.1, P1, P5, P10, P50, P90, P99, P99.9.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        self.percentile_stats_with_mode(PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics, estimated within their buckets according to
+    /// `mode`.
+    pub fn percentile_stats_with_mode(&self, mode: PercentileMode) -> PercentileStats {
+        bucket_percentile_stats(&self.scale, &self.aggregate_buckets, mode)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, in a single pass over the buckets.
+    ///
+    /// Values are estimated with [`PercentileMode::LowerBound`], like
+    /// [`percentile()`](Self::percentile). The result also holds the exact min and max.
     ///
-    /// This is used internally when calculating multiple percentiles to avoid
-    /// recalculating the total multiple times.
-    fn percentile_with_total(&self, p: f64, total: u64) -> u64 {
-        if total == 0 {
-            return 0;
+    /// # Panics
+    ///
+    /// Panics if a quantile is not in `[0, 1]`.
+    pub fn quantiles(&self, qs: &[f64]) -> Quantiles {
+        self.quantiles_with_mode(qs, PercentileMode::LowerBound)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, estimated within their buckets
+    /// according to `mode`. See [`quantiles()`](Self::quantiles).
+    pub fn quantiles_with_mode(&self, qs: &[f64], mode: PercentileMode) -> Quantiles {
+        let values = bucket_quantiles(&self.scale, &self.aggregate_buckets, qs, mode);
+        let summary = self.summary();
+        Quantiles::from_values(summary.count(), qs, values, summary.min(), summary.max())
+    }
+
+    /// Returns the number of values at or below `value`.
+    ///
+    /// This is the inverse of [`percentile()`](Self::percentile): as there, every value in a
+    /// bucket is taken as the bucket's minimum, so a bucket containing `value` is counted
+    /// entirely. The result is exact when `value` is the maximum of its bucket, in particular
+    /// for values whose bucket holds a single value: with the log scale, all values below
+    /// `2^WIDTH`.
+    pub fn rank(&self, value: u64) -> u64 {
+        let bucket_index = self.scale.calculate_bucket(value);
+        self.aggregate_buckets[..=bucket_index].iter().sum()
+    }
+
+    /// Returns the number of values strictly below `value`.
+    ///
+    /// Counts every bucket whose minimum is below `value`, see [`rank()`](Self::rank).
+    pub fn count_below(&self, value: u64) -> u64 {
+        let bucket_index = self.scale.calculate_bucket(value);
+        let rank = self.rank(value);
+        if self.scale.bucket_min_value(bucket_index) == value {
+            rank - self.aggregate_buckets[bucket_index]
+        } else {
+            rank
         }
+    }
 
-        let target = (total as f64 * p).ceil().max(1.0) as u64;
-        let mut cumulative = 0u64;
+    /// Returns the number of values strictly above `value`, i.e. `total() - rank(value)`.
+    pub fn count_above(&self, value: u64) -> u64 {
+        self.total() - self.rank(value)
+    }
+
+    /// Returns the fraction of values at or below `value`, in `[0, 1]`, or `None` if the
+    /// histogram is empty.
+    ///
+    /// For example, `fraction_at_or_below(200)` is the share of requests that completed within
+    /// 200ms when recording latencies in milliseconds. See [`rank()`](Self::rank) for how a
+    /// value inside a bucket is handled.
+    pub fn fraction_at_or_below(&self, value: u64) -> Option<f64> {
+        let total = self.total();
+        (total > 0).then(|| self.rank(value) as f64 / total as f64)
+    }
+
+    /// Returns the exact min, max, sum and sum of squares across all slots.
+    pub fn summary(&self) -> Summary {
+        let mut summary = self.previous_summary;
+        summary.merge(&self.slots.back().unwrap().summary);
+        summary
+    }
+
+    /// Returns the latest exemplar of each bucket across all slots, in ascending order of
+    /// value. See [`record_with_exemplar()`](Self::record_with_exemplar).
+    pub fn exemplars(&self) -> Vec<&Exemplar> {
+        self.bucket_exemplars().into_iter().map(|(_, e)| e).collect()
+    }
 
-        for (bucket_index, &count) in self.aggregate_buckets.iter().enumerate() {
-            cumulative += count;
-            if cumulative >= target {
-                return self.log_scale.bucket_min_value(bucket_index);
+    /// Returns the latest exemplar of each bucket across all slots, with the bucket index.
+    pub(crate) fn bucket_exemplars(&self) -> Vec<(usize, &Exemplar)> {
+        let mut latest: BTreeMap<usize, &Exemplar> = BTreeMap::new();
+        for slot in &self.slots {
+            for (index, exemplar) in &slot.exemplars {
+                let entry = latest.entry(*index).or_insert(exemplar);
+                if exemplar.timestamp >= entry.timestamp {
+                    *entry = exemplar;
+                }
             }
         }
+        latest.into_iter().collect()
+    }
 
-        0
+    /// Returns the non-empty buckets across all slots, in ascending order of value.
+    pub fn non_empty_buckets(&self) -> impl DoubleEndedIterator<Item = Bucket> + '_ {
+        non_empty_buckets(&self.scale, &self.aggregate_buckets)
     }
 
-    /// Returns common percentile statistics: samples, P0.1, P1, P5, P10, P50, P90, P99, P99.9.
-    pub fn percentile_stats(&self) -> PercentileStats {
-        let samples = self.total();
-        PercentileStats {
-            samples,
-            p0_1: self.percentile_with_total(0.001, samples),
-            p1: self.percentile_with_total(0.01, samples),
-            p5: self.percentile_with_total(0.05, samples),
-            p10: self.percentile_with_total(0.10, samples),
-            p50: self.percentile_with_total(0.50, samples),
-            p90: self.percentile_with_total(0.90, samples),
-            p99: self.percentile_with_total(0.99, samples),
-            p99_9: self.percentile_with_total(0.999, samples),
+    /// Returns a view of the slot at `index`, or `None` if the index is out of bounds.
+    ///
+    /// Index 0 is the oldest slot, index `active_slot_count() - 1` is the current slot.
+    pub fn get_slot(&self, index: usize) -> Option<SlotView<'_, T, WIDTH>> {
+        let slot = self.slots.get(index)?;
+        Some(SlotView::new(&self.scale, slot))
+    }
+
+    /// Returns views of the active slots, from oldest to newest.
+    pub fn slots(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = SlotView<'_, T, WIDTH>> + ExactSizeIterator {
+        self.slots.iter().map(|slot| SlotView::new(&self.scale, slot))
+    }
+
+    /// Returns the total number of values recorded in the slots within `range`.
+    ///
+    /// Slot indices are the same as in [`get_slot()`](Self::get_slot).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the active slots, like slice indexing.
+    pub fn range_total(&self, range: impl RangeBounds<usize>) -> u64 {
+        let range = self.slot_range(range);
+        self.slots.range(range).map(|slot| slot.buckets.total()).sum()
+    }
+
+    /// Calculates the value at the given percentile over the slots within `range`.
+    ///
+    /// See [`percentile()`](Self::percentile) and [`range_total()`](Self::range_total).
+    pub fn range_percentile(&self, range: impl RangeBounds<usize>, p: f64) -> u64 {
+        let buckets = self.range_buckets(range);
+        let total = buckets.iter().sum();
+        bucket_percentile(&self.scale, &buckets, total, p, PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics over the slots within `range`.
+    ///
+    /// See [`percentile_stats()`](Self::percentile_stats) and
+    /// [`range_total()`](Self::range_total).
+    pub fn range_percentile_stats(&self, range: impl RangeBounds<usize>) -> PercentileStats {
+        let buckets = self.range_buckets(range);
+        bucket_percentile_stats(&self.scale, &buckets, PercentileMode::LowerBound)
+    }
+
+    /// Returns the exact statistics of the slots within `range`.
+    ///
+    /// See [`summary()`](Self::summary) and [`range_total()`](Self::range_total).
+    pub fn range_summary(&self, range: impl RangeBounds<usize>) -> Summary {
+        let range = self.slot_range(range);
+        let mut summary = Summary::EMPTY;
+        for slot in self.slots.range(range) {
+            summary.merge(&slot.summary);
+        }
+        summary
+    }
+
+    /// Returns the total number of values recorded in the newest `n` slots, including the
+    /// current one.
+    ///
+    /// If `n` exceeds the number of active slots, all slots are included.
+    pub fn last_total(&self, n: usize) -> u64 {
+        self.range_total(self.last_range(n))
+    }
+
+    /// Calculates the value at the given percentile over the newest `n` slots.
+    ///
+    /// See [`last_total()`](Self::last_total).
+    pub fn last_percentile(&self, n: usize, p: f64) -> u64 {
+        self.range_percentile(self.last_range(n), p)
+    }
+
+    /// Returns common percentile statistics over the newest `n` slots.
+    ///
+    /// See [`last_total()`](Self::last_total).
+    pub fn last_percentile_stats(&self, n: usize) -> PercentileStats {
+        self.range_percentile_stats(self.last_range(n))
+    }
+
+    /// Returns the exact statistics of the newest `n` slots.
+    ///
+    /// See [`last_total()`](Self::last_total).
+    pub fn last_summary(&self, n: usize) -> Summary {
+        self.range_summary(self.last_range(n))
+    }
+
+    /// Sums the bucket counts of the slots within `range`.
+    ///
+    /// Borrows the aggregate when `range` covers all active slots.
+    fn range_buckets(&self, range: impl RangeBounds<usize>) -> Cow<'_, [u64]> {
+        let range = self.slot_range(range);
+        if range.len() == self.slots.len() {
+            return Cow::Borrowed(&self.aggregate_buckets);
         }
+
+        let mut buckets = vec![0u64; self.aggregate_buckets.len()];
+        for slot in self.slots.range(range) {
+            for (index, count) in slot.buckets.iter() {
+                // Cannot overflow: the aggregate holds the sum of all slots.
+                buckets[index] += count;
+            }
+        }
+        Cow::Owned(buckets)
+    }
+
+    /// Resolves `range` against the active slots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or decreasing.
+    fn slot_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        let len = self.slots.len();
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.checked_add(1).expect("slot range start overflows"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.checked_add(1).expect("slot range end overflows"),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => len,
+        };
+
+        assert!(
+            start <= end && end <= len,
+            "slot range {}..{} out of bounds for {} active slots",
+            start,
+            end,
+            len
+        );
+        start..end
+    }
+
+    /// Returns the index range of the newest `n` slots.
+    fn last_range(&self, n: usize) -> Range<usize> {
+        let len = self.slots.len();
+        len.saturating_sub(n)..len
     }
 
     #[cfg(test)]
@@ -245,26 +849,128 @@ impl<T> Histogram<T> {
 
     #[cfg(test)]
     pub(crate) fn num_buckets(&self) -> usize {
-        self.log_scale.num_buckets()
+        self.scale.num_buckets()
+    }
+}
+
+/// Calculates the value at percentile `p` of `buckets`, whose counts sum to `total`.
+///
+/// The value is estimated within the bucket containing the percentile according to `mode`.
+/// Returns `0` if `total` is 0.
+pub(crate) fn bucket_percentile(
+    scale: &impl BucketScale,
+    buckets: &[u64],
+    total: u64,
+    p: f64,
+    mode: PercentileMode,
+) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+
+    let target = (total as f64 * p).ceil().max(1.0) as u64;
+    bucket_value_at_rank(scale, buckets, target, mode)
+}
+
+/// Estimates the value of rank `rank` (1-based, in ascending order) in `buckets` according to
+/// `mode`.
+///
+/// Returns `0` if there are fewer than `rank` values.
+pub(crate) fn bucket_value_at_rank(
+    scale: &impl BucketScale,
+    buckets: &[u64],
+    rank: u64,
+    mode: PercentileMode,
+) -> u64 {
+    let mut cumulative = 0u64;
+
+    for (bucket_index, &count) in buckets.iter().enumerate() {
+        if cumulative + count >= rank {
+            return mode.estimate(
+                scale.bucket_min_value(bucket_index),
+                scale.bucket_max_value(bucket_index),
+                rank - cumulative,
+                count,
+            );
+        }
+        cumulative += count;
+    }
+
+    0
+}
+
+/// Returns the common percentile statistics of `buckets`, estimated according to `mode`.
+pub(crate) fn bucket_percentile_stats(
+    scale: &impl BucketScale,
+    buckets: &[u64],
+    mode: PercentileMode,
+) -> PercentileStats {
+    let v = bucket_quantiles(scale, buckets, &PercentileStats::QUANTILES, mode);
+    PercentileStats::from_values(buckets.iter().sum(), &v)
+}
+
+/// Records values to the current slot. See [`Histogram::record_iter`].
+impl<T, const WIDTH: usize> Extend<u64> for Histogram<T, WIDTH> {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        self.record_iter(iter);
+    }
+}
+
+/// Records values to the current slot. See [`Histogram::record_iter`].
+impl<'a, T, const WIDTH: usize> Extend<&'a u64> for Histogram<T, WIDTH> {
+    fn extend<I: IntoIterator<Item = &'a u64>>(&mut self, iter: I) {
+        self.record_iter(iter.into_iter().copied());
+    }
+}
+
+/// Merges `other` into the current slot. See [`Histogram::merge`].
+///
+/// # Panics
+///
+/// Panics if the histograms use scales with different buckets.
+impl<T, U, const WIDTH: usize> AddAssign<&Histogram<U, WIDTH>> for Histogram<T, WIDTH> {
+    fn add_assign(&mut self, other: &Histogram<U, WIDTH>) {
+        if let Err(e) = self.merge(other) {
+            panic!("cannot add histograms: {}", e);
+        }
+    }
+}
+
+/// Subtracts `other` from the current slot. See [`Histogram::subtract`].
+///
+/// # Panics
+///
+/// Panics if the histograms use scales with different buckets or the subtraction underflows.
+impl<T, U, const WIDTH: usize> SubAssign<&Histogram<U, WIDTH>> for Histogram<T, WIDTH> {
+    fn sub_assign(&mut self, other: &Histogram<U, WIDTH>) {
+        if let Err(e) = self.subtract(other) {
+            panic!("cannot subtract histograms: {}", e);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::LazyLock;
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
     use super::*;
+    use crate::histogram::ExplicitBoundsScale;
+    use crate::histogram::LinearScale;
     use crate::histogram::LogScale3;
     use crate::histogram::LogScaleConfig;
 
     #[test]
     fn test_slot_clear() {
         let mut slot: Slot<String> = Slot::new(10);
-        slot.buckets[0] = 5;
-        slot.buckets[5] = 10;
+        slot.buckets.add(0, 5);
+        slot.buckets.add(5, 10);
         slot.data = Some("test".to_string());
 
         slot.clear();
 
-        assert!(slot.buckets.iter().all(|&c| c == 0));
+        assert_eq!(slot.buckets.total(), 0);
         assert_eq!(slot.data, None);
     }
 
@@ -420,6 +1126,59 @@ mod tests {
         assert!((896..=1000).contains(&p80), "P80 = {}", p80);
     }
 
+    fn check_percentiles_for_width<const WIDTH: usize>() {
+        let mut hist: Histogram<(), WIDTH> = Histogram::new();
+        assert_eq!(hist.num_buckets(), LogScaleConfig::<WIDTH>::BUCKETS);
+
+        for v in 1..=10_000 {
+            hist.record(v);
+        }
+        assert_eq!(hist.total(), 10_000);
+
+        let scale = LogScale::<WIDTH>::new();
+        let max_error = 1.0 / LogScaleConfig::<WIDTH>::GROUP_SIZE as f64;
+
+        let stats = hist.percentile_stats();
+        for (p, got) in [(0.01, stats.p1), (0.5, stats.p50), (0.99, stats.p99)] {
+            let exact = (10_000.0 * p) as u64;
+            assert_eq!(got, scale.bucket_min_value(scale.calculate_bucket(exact)));
+            let error = (exact - got) as f64 / exact as f64;
+            assert!(
+                error <= max_error,
+                "WIDTH={} p={} exact={} got={}",
+                WIDTH,
+                p,
+                exact,
+                got
+            );
+        }
+    }
+
+    #[test]
+    fn test_percentiles_for_supported_widths() {
+        check_percentiles_for_width::<1>();
+        check_percentiles_for_width::<2>();
+        check_percentiles_for_width::<3>();
+        check_percentiles_for_width::<4>();
+        check_percentiles_for_width::<5>();
+        check_percentiles_for_width::<6>();
+        check_percentiles_for_width::<7>();
+        check_percentiles_for_width::<8>();
+        check_percentiles_for_width::<10>();
+    }
+
+    #[test]
+    fn test_width_4_precision() {
+        let mut hist: Histogram<(), 4> = Histogram::new();
+        assert_eq!(hist.num_buckets(), 496);
+
+        hist.record(100);
+        // WIDTH=4 splits [64, 128) into 8 buckets of 8: 100 falls into [96, 104).
+        assert_eq!(hist.percentile(0.5), 96);
+        hist.record(u64::MAX);
+        assert_eq!(hist.get_bucket(495), 1);
+    }
+
     // Multi-slot tests
 
     #[test]
@@ -475,7 +1234,10 @@ mod tests {
         assert_eq!(hist.total(), 2); // 100 in slot 0, 200 in slot 1
 
         // Advance again - evicts oldest (slot with 100), adds new slot
-        assert_eq!(hist.advance(40), 4);
+        let evicted = hist.advance_and_take(40).unwrap();
+        assert_eq!(evicted.data(), None);
+        assert_eq!(evicted.total(), 1);
+        assert_eq!(evicted.percentile(0.5), 96);
         assert_eq!(hist.active_slot_count(), 4);
         assert_eq!(hist.total(), 1); // Only 200 remains (in what is now slot 0)
 
@@ -488,6 +1250,34 @@ mod tests {
         assert_eq!(hist.current_slot().data, Some(40));
     }
 
+    #[test]
+    fn test_advance_and_take() {
+        let mut hist: Histogram<&str> = Histogram::with_slots(2);
+        hist.advance("minute-1");
+        hist.record(5);
+        hist.record(5);
+        hist.record(1000);
+
+        // The initial slot has no metadata.
+        let evicted = hist.advance_and_take("minute-2").unwrap();
+        assert!(evicted.data().is_none());
+        assert_eq!(evicted.total(), 0);
+        hist.record(7);
+
+        let evicted = hist.advance_and_take("minute-3").unwrap();
+        assert_eq!(evicted.data(), Some(&"minute-1"));
+        assert_eq!(evicted.total(), 3);
+        assert_eq!(evicted.buckets().len(), 252);
+        assert_eq!(evicted.buckets()[5], 2);
+        assert_eq!(evicted.percentile_stats().p50, 5);
+        assert_eq!(hist.total(), 1);
+
+        let archived = evicted.clone().into_histogram();
+        assert_eq!(archived.total(), 3);
+        assert_eq!(archived.percentile_stats(), evicted.percentile_stats());
+        assert_eq!(evicted.into_data(), Some("minute-1"));
+    }
+
     #[test]
     fn test_advance_capacity_stays_constant() {
         let mut hist: Histogram<u64> = Histogram::with_slots(3);
@@ -558,6 +1348,656 @@ mod tests {
         assert!((48..=52).contains(&p50), "P50 = {}", p50);
     }
 
+    #[test]
+    fn test_range_queries() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(4);
+
+        // Slot i holds 10 values of (i + 1) * 100.
+        for i in 0..4u64 {
+            if i > 0 {
+                hist.advance(i);
+            }
+            for _ in 0..10 {
+                hist.record((i + 1) * 100);
+            }
+        }
+
+        assert_eq!(hist.range_total(..), 40);
+        assert_eq!(hist.range_total(1..3), 20);
+        assert_eq!(hist.range_total(3..=3), 10);
+        assert_eq!(hist.range_total(2..2), 0);
+
+        assert_eq!(hist.range_percentile(..1, 0.99), 96);
+        assert_eq!(hist.range_percentile(2.., 0.0), 256);
+        assert_eq!(hist.range_percentile_stats(..), hist.percentile_stats());
+        assert_eq!(hist.range_percentile_stats(1..1), PercentileStats {
+            samples: 0,
+            p0_1: 0,
+            p1: 0,
+            p5: 0,
+            p10: 0,
+            p50: 0,
+            p90: 0,
+            p99: 0,
+            p99_9: 0,
+        });
+
+        let stats = hist.range_percentile_stats(1..=2);
+        assert_eq!(stats.samples, 20);
+        assert_eq!(stats.p10, 192);
+        assert_eq!(stats.p90, 256);
+    }
+
+    #[test]
+    fn test_last_queries() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(3);
+        hist.record(1);
+        hist.advance(1);
+        hist.record(5);
+        hist.record(5);
+
+        assert_eq!(hist.last_total(1), 2);
+        assert_eq!(hist.last_total(2), 3);
+        assert_eq!(hist.last_total(10), 3);
+        assert_eq!(hist.last_total(0), 0);
+
+        assert_eq!(hist.last_percentile(1, 0.0), 5);
+        assert_eq!(hist.last_percentile(2, 0.0), 1);
+        assert_eq!(hist.last_percentile_stats(1).samples, 2);
+        assert_eq!(hist.last_percentile_stats(5), hist.percentile_stats());
+    }
+
+    #[test]
+    #[should_panic(expected = "slot range 1..3 out of bounds for 2 active slots")]
+    fn test_range_out_of_bounds_panics() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(4);
+        hist.advance(1);
+        hist.range_total(1..3);
+    }
+
+    #[test]
+    fn test_slot_views() {
+        let mut hist: Histogram<&str> = Histogram::with_slots(2);
+        hist.record(3);
+        hist.advance("a");
+        hist.record(7);
+        hist.record(7);
+        hist.advance("b");
+        hist.record(42);
+
+        let views: Vec<_> = hist.slots().map(|s| (s.data().copied(), s.total())).collect();
+        assert_eq!(views, vec![(Some("a"), 2), (Some("b"), 1)]);
+
+        let newest = hist.slots().next_back().unwrap();
+        assert_eq!(newest.percentile(0.5), 40);
+        assert_eq!(newest.percentile_stats().samples, 1);
+
+        let oldest = hist.get_slot(0).unwrap();
+        assert_eq!(oldest.data(), Some(&"a"));
+        assert_eq!(oldest.percentile(1.0), 7);
+        assert!(hist.get_slot(2).is_none());
+    }
+
+    #[test]
+    fn test_summary_tracking() {
+        let mut hist: Histogram = Histogram::new();
+        assert_eq!(hist.summary(), Summary::EMPTY);
+
+        for v in [100, 5, 1000] {
+            hist.record(v);
+        }
+
+        let summary = hist.summary();
+        assert_eq!(summary.count(), 3);
+        assert_eq!(summary.min(), Some(5));
+        assert_eq!(summary.max(), Some(1000));
+        assert_eq!(summary.sum(), 1105);
+        assert_eq!(summary.sum_of_squares(), 10_000 + 25 + 1_000_000);
+        assert_eq!(hist.get_slot(0).unwrap().summary(), summary);
+    }
+
+    #[test]
+    fn test_summary_on_eviction() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(3);
+        hist.record(1);
+        hist.record(10_000);
+        hist.advance(1);
+        hist.record(50);
+        hist.advance(2);
+        hist.record(70);
+
+        assert_eq!(hist.summary().min(), Some(1));
+        assert_eq!(hist.summary().max(), Some(10_000));
+
+        // Evicting the first slot recomputes min and max from the remaining ones.
+        let evicted = hist.advance_and_take(3).unwrap();
+        assert_eq!(evicted.summary().sum(), 10_001);
+
+        let summary = hist.summary();
+        assert_eq!(summary.count(), 2);
+        assert_eq!(summary.min(), Some(50));
+        assert_eq!(summary.max(), Some(70));
+        assert_eq!(summary.sum(), 120);
+        assert_eq!(summary.mean(), Some(60.0));
+        assert_eq!(summary.stddev(), Some(10.0));
+
+        assert_eq!(hist.range_summary(..1).sum(), 50);
+        assert_eq!(hist.last_summary(2).max(), Some(70));
+        assert_eq!(hist.last_summary(1), Summary::EMPTY);
+
+        hist.advance(4);
+        hist.advance(5);
+        assert_eq!(hist.summary(), Summary::EMPTY);
+    }
+
+    #[test]
+    fn test_summary_merge_subtract_difference() {
+        let mut a: Histogram = Histogram::new();
+        let mut b: Histogram = Histogram::new();
+        a.record(1);
+        a.record(100);
+        b.record(5000);
+
+        a.merge(&b).unwrap();
+        assert_eq!(a.summary().max(), Some(5000));
+        assert_eq!(a.summary().sum(), 5101);
+
+        // Exact min and max of the remainder are unknown: narrowed to the remaining buckets.
+        let diff = a.difference(&b).unwrap();
+        assert_eq!(diff.summary().count(), 2);
+        assert_eq!(diff.summary().sum(), 101);
+        assert_eq!(diff.summary().min(), Some(1));
+        assert_eq!(diff.summary().max(), Some(111));
+
+        a.subtract(&b).unwrap();
+        assert_eq!(a.summary(), diff.summary());
+        assert_eq!(a.get_slot(0).unwrap().summary(), diff.summary());
+    }
+
+    /// A small deterministic xorshift generator, to avoid a dependency for random samples.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// A value whose magnitude is spread over `1..2^max_bits`.
+        fn next_log_uniform(&mut self, max_bits: u32) -> u64 {
+            let bits = (self.next() % max_bits as u64) as u32 + 1;
+            self.next() >> (64 - bits)
+        }
+    }
+
+    /// Checks every mode against exact percentiles of random samples, returning the mean
+    /// relative error of each mode.
+    fn check_percentile_modes<const WIDTH: usize>(samples: &[u64]) -> Vec<f64> {
+        let mut hist: Histogram<(), WIDTH> = Histogram::new();
+        for &v in samples {
+            hist.record(v);
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+
+        let group_size = LogScaleConfig::<WIDTH>::GROUP_SIZE as f64;
+        let modes = [
+            (PercentileMode::LowerBound, 1.0 / (group_size + 1.0)),
+            (PercentileMode::UpperBound, 1.0 / group_size),
+            (PercentileMode::Midpoint, 1.0 / (2.0 * group_size)),
+            (PercentileMode::Interpolate, 1.0 / group_size),
+        ];
+
+        let percentiles: Vec<f64> = (0..=1000).map(|i| i as f64 / 1000.0).collect();
+        let mut mean_errors = vec![];
+        for (mode, bound) in modes {
+            let mut error_sum = 0.0;
+            for &p in &percentiles {
+                let rank = (sorted.len() as f64 * p).ceil().max(1.0) as usize;
+                let exact = sorted[rank - 1];
+                let estimate = hist.percentile_with_mode(p, mode);
+
+                match mode {
+                    PercentileMode::LowerBound => assert!(estimate <= exact),
+                    PercentileMode::UpperBound => assert!(estimate >= exact),
+                    _ => {}
+                }
+
+                let error = (estimate as f64 - exact as f64).abs() / exact.max(1) as f64;
+                assert!(
+                    error <= bound,
+                    "WIDTH={} mode={:?} p={} exact={} estimate={}",
+                    WIDTH,
+                    mode,
+                    p,
+                    exact,
+                    estimate
+                );
+                error_sum += error;
+            }
+            mean_errors.push(error_sum / percentiles.len() as f64);
+        }
+        mean_errors
+    }
+
+    #[test]
+    fn test_percentile_modes_against_exact() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+        let log_uniform: Vec<u64> = (0..20_000).map(|_| rng.next_log_uniform(40)).collect();
+        let uniform: Vec<u64> = (0..20_000).map(|_| 1_000 + rng.next() % 1_000_000).collect();
+
+        for samples in [&log_uniform, &uniform] {
+            let errors = check_percentile_modes::<3>(samples);
+            check_percentile_modes::<4>(samples);
+
+            // On smooth data, interpolation beats both bounds.
+            let (lower, upper, interpolate) = (errors[0], errors[1], errors[3]);
+            assert!(interpolate < lower, "{:?}", errors);
+            assert!(interpolate < upper, "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_percentile_stats_with_mode() {
+        let mut hist: Histogram = Histogram::new();
+        for _ in 0..10 {
+            hist.record(100);
+        }
+
+        assert_eq!(
+            hist.percentile_stats_with_mode(PercentileMode::LowerBound),
+            hist.percentile_stats()
+        );
+        assert_eq!(
+            hist.percentile_stats_with_mode(PercentileMode::UpperBound).p50,
+            111
+        );
+        assert_eq!(
+            hist.percentile_stats_with_mode(PercentileMode::Midpoint).p50,
+            103
+        );
+        let interpolated = hist.percentile_stats_with_mode(PercentileMode::Interpolate);
+        assert_eq!(interpolated.samples, 10);
+        assert_eq!(interpolated.p0_1, 96);
+        assert_eq!(interpolated.p50, 103);
+        assert_eq!(interpolated.p99_9, 111);
+
+        let empty: Histogram = Histogram::new();
+        assert_eq!(
+            empty.percentile_with_mode(0.5, PercentileMode::UpperBound),
+            0
+        );
+    }
+
+    #[test]
+    fn test_quantiles() {
+        let mut hist: Histogram = Histogram::new();
+        for v in 1..=1000 {
+            hist.record(v);
+        }
+
+        let q = hist.quantiles(&[0.5, 0.99, 0.9999]);
+        assert_eq!(q.samples, 1000);
+        assert_eq!(q.get(0.5), Some(hist.percentile(0.5)));
+        assert_eq!(q.get(0.99), Some(hist.percentile(0.99)));
+        assert_eq!(q.get(0.9999), Some(896));
+        assert_eq!((q.min, q.max), (Some(1), Some(1000)));
+        assert_eq!(
+            q.to_string(),
+            "[samples: 1000, P50: 448, P99: 896, P99.99: 896, min: 1, max: 1000]"
+        );
+
+        let q = hist.quantiles_with_mode(&[0.9999], PercentileMode::UpperBound);
+        assert_eq!(q.get(0.9999), Some(1023));
+
+        let q = Histogram::<()>::new().quantiles(&[0.5]);
+        assert_eq!((q.samples, q.get(0.5), q.max), (0, Some(0), None));
+    }
+
+    #[test]
+    fn test_percentile_stats_matches_percentile() {
+        let mut rng = XorShift(42);
+        let mut hist: Histogram = Histogram::new();
+        for _ in 0..10_000 {
+            hist.record(rng.next_log_uniform(30));
+        }
+
+        let stats = hist.percentile_stats();
+        let expected =
+            [0.001, 0.01, 0.05, 0.10, 0.50, 0.90, 0.99, 0.999].map(|p| hist.percentile(p));
+        assert_eq!(
+            [
+                stats.p0_1,
+                stats.p1,
+                stats.p5,
+                stats.p10,
+                stats.p50,
+                stats.p90,
+                stats.p99,
+                stats.p99_9
+            ],
+            expected
+        );
+    }
+
+    #[test]
+    fn test_record_n() {
+        let mut expected: Histogram<u64> = Histogram::with_slots(2);
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        for _ in 0..37 {
+            expected.record(4096);
+        }
+        hist.record_n(4096, 37);
+        hist.record_n(1, 0);
+        assert_eq!(hist, expected);
+
+        hist.advance(1);
+        hist.record_n(5, 2);
+        assert_eq!(hist.total(), 39);
+        assert_eq!(hist.last_total(1), 2);
+        assert_eq!(hist.summary().sum(), 37 * 4096 + 10);
+    }
+
+    #[test]
+    fn test_bulk_record() {
+        let mut rng = XorShift(7);
+        let values: Vec<u64> = (0..10_000).map(|_| rng.next_log_uniform(64)).collect();
+
+        let mut expected: Histogram<u64> = Histogram::with_slots(2);
+        expected.record(3);
+        expected.advance(1);
+        for &v in &values {
+            expected.record(v);
+        }
+
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        hist.record(3);
+        hist.advance(1);
+        hist.record_slice(&values);
+        assert_eq!(hist, expected);
+
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        hist.record(3);
+        hist.advance(1);
+        hist.extend(&values[..5000]);
+        hist.extend(values[5000..].iter().copied());
+        assert_eq!(hist, expected);
+
+        hist.record_slice(&[]);
+        assert_eq!(hist, expected);
+    }
+
+    #[test]
+    fn test_rank() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        assert_eq!(hist.rank(100), 0);
+        assert_eq!(hist.fraction_at_or_below(100), None);
+
+        // 1..=7 have single-value buckets; 100 and 110 share the bucket [96, 111].
+        for v in 1..=7 {
+            hist.record(v);
+        }
+        hist.advance(1);
+        hist.record(100);
+        hist.record(110);
+        hist.record(1000);
+
+        assert_eq!(hist.rank(0), 0);
+        assert_eq!(hist.rank(3), 3);
+        assert_eq!(hist.count_below(3), 2);
+        assert_eq!(hist.count_above(3), 7);
+
+        // Values inside a bucket count the whole bucket.
+        assert_eq!(hist.rank(95), 7);
+        assert_eq!(hist.rank(96), 9);
+        assert_eq!(hist.rank(100), 9);
+        assert_eq!(hist.count_below(96), 7);
+        assert_eq!(hist.count_below(100), 9);
+        assert_eq!(hist.count_above(111), 1);
+
+        assert_eq!(hist.rank(u64::MAX), 10);
+        assert_eq!(hist.count_above(u64::MAX), 0);
+        assert_eq!(hist.fraction_at_or_below(111), Some(0.9));
+        assert_eq!(hist.fraction_at_or_below(0), Some(0.0));
+    }
+
+    #[test]
+    fn test_rank_inverts_percentile() {
+        let mut rng = XorShift(11);
+        let mut hist: Histogram = Histogram::new();
+        for _ in 0..1000 {
+            hist.record(rng.next_log_uniform(40));
+        }
+
+        let total = hist.total();
+        for p in [0.0, 0.1, 0.5, 0.9, 0.99, 1.0] {
+            let value = hist.percentile(p);
+            let target = ((total as f64 * p).ceil() as u64).max(1);
+            assert!(hist.rank(value) >= target);
+            assert!(hist.count_below(value) < target);
+            assert_eq!(hist.rank(value) + hist.count_above(value), total);
+        }
+    }
+
+    #[test]
+    fn test_non_empty_buckets() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        hist.record(100);
+        hist.record(110);
+        hist.advance(1);
+        hist.record(5);
+        hist.record(105);
+
+        let aggregate: Vec<(u64, u128, u64)> =
+            hist.non_empty_buckets().map(|b| (b.lower_bound, b.upper_bound, b.count)).collect();
+        assert_eq!(aggregate, vec![(5, 6, 1), (96, 112, 3)]);
+        assert_eq!(
+            hist.non_empty_buckets().map(|b| b.count).sum::<u64>(),
+            hist.total()
+        );
+
+        let oldest: Vec<(u64, u64)> = hist
+            .get_slot(0)
+            .unwrap()
+            .non_empty_buckets()
+            .map(|b| (b.lower_bound, b.count))
+            .collect();
+        assert_eq!(oldest, vec![(96, 2)]);
+
+        let evicted = hist.advance_and_take(2).unwrap();
+        assert_eq!(evicted.non_empty_buckets().count(), 1);
+        assert_eq!(hist.non_empty_buckets().next_back().unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_sparse_slots() {
+        let dense_slot = 252 * size_of::<u64>();
+
+        // A minute of per-second slots, each seeing a few distinct latencies.
+        let mut hist: Histogram = Histogram::with_slots(60);
+        for _ in 0..60 {
+            hist.advance(());
+            hist.record_slice(&[900, 1000, 1100, 1000, 5000]);
+        }
+        assert!(hist.slots.iter().all(|slot| slot.buckets.is_sparse()));
+        assert!(hist.memory_usage() < 60 * dense_slot / 4);
+
+        // A slot using most buckets switches to dense storage, without changing results.
+        let before = hist.clone();
+        let mut rng = XorShift(3);
+        let values: Vec<u64> = (0..10_000).map(|_| rng.next_log_uniform(64)).collect();
+        hist.record_slice(&values);
+        assert!(!hist.current_slot().buckets.is_sparse());
+        assert!(hist.memory_usage() > before.memory_usage() + dense_slot);
+
+        let mut expected = before.clone();
+        for &v in &values {
+            expected.record(v);
+        }
+        assert_eq!(hist, expected);
+        assert_eq!(hist.percentile_stats(), expected.percentile_stats());
+
+        let mut added: Histogram = Histogram::new();
+        added.record_slice(&values);
+        hist.subtract(&added).unwrap();
+        assert_eq!(hist.aggregate_buckets, before.aggregate_buckets);
+
+        // A single slot is dense from the start, also after advance(), clear() and decode().
+        let mut single: Histogram = Histogram::new();
+        assert!(!single.current_slot().buckets.is_sparse());
+        single.record(5);
+        single.advance(());
+        assert!(!single.current_slot().buckets.is_sparse());
+        single.record(5);
+        let decoded: Histogram = Histogram::decode(&single.encode()).unwrap();
+        assert!(!decoded.current_slot().buckets.is_sparse());
+        assert_eq!(decoded.aggregate_buckets, single.aggregate_buckets);
+        single.clear();
+        assert!(!single.current_slot().buckets.is_sparse());
+    }
+
+    #[test]
+    fn test_exemplars() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        hist.record(5);
+        hist.record_with_exemplar(Exemplar::new(100, "a").with_timestamp(at(2)));
+        // 101 shares the bucket [96, 111]: only the latest exemplar is kept.
+        hist.record_with_exemplar(Exemplar::new(101, "b").with_timestamp(at(1)));
+        hist.record_with_exemplar(Exemplar::new(7, "c").with_timestamp(at(1)));
+        assert_eq!(hist.total(), 4);
+        let ids = |hist: &Histogram<u64>| {
+            hist.exemplars().iter().map(|e| e.id.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&hist), vec!["c", "a"]);
+
+        // Across slots, the latest exemplar of the bucket wins.
+        hist.advance(1);
+        hist.record_with_exemplar(Exemplar::new(110, "d").with_timestamp(at(3)));
+        assert_eq!(ids(&hist), vec!["c", "d"]);
+        assert_eq!(hist.get_slot(1).unwrap().exemplars().count(), 1);
+
+        // Eviction drops the exemplars of the slot.
+        let evicted = hist.advance_and_take(2).unwrap();
+        let evicted_ids: Vec<&str> = evicted.exemplars().iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(evicted_ids, vec!["c", "a"]);
+        assert_eq!(ids(&hist), vec!["d"]);
+
+        // Merge keeps the exemplars of `other`; subtract drops those of emptied buckets.
+        let mut other: Histogram<u64> = Histogram::new();
+        other.record_with_exemplar(Exemplar::new(7, "e").with_timestamp(at(4)));
+        hist.merge(&other).unwrap();
+        assert_eq!(ids(&hist), vec!["e", "d"]);
+        hist.subtract(&other).unwrap();
+        assert_eq!(ids(&hist), vec!["d"]);
+        assert!(hist.memory_usage() > Histogram::<u64>::with_slots(2).memory_usage());
+    }
+
+    #[test]
+    fn test_take_snapshot() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(2);
+        hist.record_slice(&[1, 5, 100]);
+        hist.advance(1);
+        hist.record_with_exemplar(Exemplar::new(1000, "slow"));
+        let stats = hist.percentile_stats();
+        let summary = hist.summary();
+
+        let snapshot = hist.take_snapshot();
+        assert_eq!(snapshot.total(), 4);
+        assert_eq!(snapshot.percentile_stats(), stats);
+        assert_eq!(snapshot.summary(), summary);
+        assert_eq!(
+            snapshot.percentile(0.5),
+            hist_of(&[1, 5, 100, 1000]).percentile(0.5)
+        );
+        assert_eq!(snapshot.quantiles(&[1.0]).get(1.0), Some(896));
+        assert_eq!(snapshot.exemplars()[0].id, "slow");
+        assert_eq!(snapshot.non_empty_buckets().count(), 4);
+
+        // The histogram starts a new interval, with the same capacity.
+        assert_eq!(hist.total(), 0);
+        assert_eq!(hist.summary(), Summary::EMPTY);
+        assert!(hist.exemplars().is_empty());
+        assert_eq!((hist.active_slot_count(), hist.capacity()), (1, 2));
+        hist.record(7);
+        let next = hist.take_snapshot();
+        assert_eq!(next.total(), 1);
+        assert_eq!(snapshot.clone(), snapshot);
+
+        let mut merged: Histogram = snapshot.into_histogram();
+        merged.merge(&next.into_histogram::<()>()).unwrap();
+        assert_eq!(merged.total(), 5);
+        assert_eq!(merged.summary().min(), Some(1));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(3);
+        hist.record(10);
+        hist.advance(1);
+        hist.record(20);
+        hist.clear();
+
+        let mut expected: Histogram<u64> = Histogram::with_slots(3);
+        assert_eq!(hist, expected);
+        hist.record(30);
+        expected.record(30);
+        assert_eq!(hist, expected);
+        assert_eq!(hist.get_slot(0).unwrap().data(), None);
+    }
+
+    fn hist_of(values: &[u64]) -> Histogram {
+        let mut hist: Histogram = Histogram::new();
+        hist.record_slice(values);
+        hist
+    }
+
+    #[test]
+    fn test_custom_scale() {
+        let mut hist: Histogram = Histogram::with_scale(LinearScale::new(1, 65), 2);
+        assert_eq!(hist.num_buckets(), 65);
+        for depth in [0, 3, 3, 10, 64, 500] {
+            hist.record(depth);
+        }
+        assert_eq!(hist.get_bucket(3), 2);
+        assert_eq!(hist.get_bucket(64), 2);
+        assert_eq!(hist.percentile(0.5), 3);
+        assert_eq!(hist.percentile(0.6), 10);
+        assert_eq!(
+            hist.percentile_with_mode(1.0, PercentileMode::LowerBound),
+            64
+        );
+        assert_eq!(hist.summary().max(), Some(500));
+
+        let buckets: Vec<Bucket> = hist.non_empty_buckets().collect();
+        assert_eq!(buckets[1], Bucket {
+            lower_bound: 3,
+            upper_bound: 4,
+            count: 2
+        });
+        assert_eq!(buckets[3].upper_bound, 1 << 64);
+
+        // Merging needs the same buckets, not the same scale instance.
+        let mut other: Histogram = Histogram::with_scale(LinearScale::new(1, 65), 1);
+        other.record(3);
+        hist.merge(&other).unwrap();
+        assert_eq!(hist.get_bucket(3), 3);
+        assert_eq!(
+            hist.merge(&Histogram::<()>::new()),
+            Err(HistogramError::ScaleMismatch)
+        );
+
+        let statuses = ExplicitBoundsScale::new([100, 200, 300, 400, 500, 600]);
+        let mut hist: Histogram = Histogram::with_scale(statuses, 1);
+        hist.record_slice(&[200, 204, 404, 503]);
+        let q = hist.quantiles(&[0.5, 1.0]);
+        assert_eq!((q.get(0.5), q.get(1.0)), (Some(200), Some(500)));
+        assert_eq!(hist.rank(299), 2);
+    }
+
     #[test]
     #[should_panic(expected = "capacity must be at least 1")]
     fn test_with_slots_zero_panics() {
@@ -575,7 +2015,7 @@ mod tests {
 
         // Helper to compute manual total from slots
         let manual_total = |h: &Histogram<u64>| -> u64 {
-            (0..h.active_slot_count()).flat_map(|i| h.slot(i).unwrap().buckets.iter()).sum()
+            (0..h.active_slot_count()).map(|i| h.slot(i).unwrap().buckets.total()).sum()
         };
 
         assert_eq!(hist.total(), manual_total(&hist));
@@ -602,4 +2042,139 @@ mod tests {
         assert_eq!(hist.total(), manual_total(&hist));
         assert_eq!(hist.total(), 4); // 7 values recorded in slots 1,2 minus evicted = 3 + 1 = 4
     }
+
+    // Merge and subtraction tests
+
+    #[test]
+    fn test_merge() {
+        let mut a: Histogram = Histogram::new();
+        let mut b: Histogram<u64> = Histogram::with_slots(2);
+
+        a.record(1);
+        a.record(100);
+        b.record(100);
+        b.advance(1);
+        b.record(1000);
+
+        a.merge(&b).unwrap();
+
+        assert_eq!(a.total(), 4);
+        assert_eq!(a.get_bucket(LogScale3::calculate_bucket_uncached(100)), 2);
+        assert_eq!(a.get_bucket(LogScale3::calculate_bucket_uncached(1000)), 1);
+        assert_eq!(a.current_slot().buckets.to_dense(), a.aggregate_buckets);
+    }
+
+    #[test]
+    fn test_merge_into_current_slot() {
+        let mut a: Histogram<u64> = Histogram::with_slots(2);
+        a.record(1);
+        a.advance(1);
+
+        let mut b: Histogram = Histogram::new();
+        b.record(2);
+
+        a += &b;
+        assert_eq!(a.total(), 2);
+        assert_eq!(a.current_slot().buckets.get(2), 1);
+        assert_eq!(a.slot(0).unwrap().buckets.get(2), 0);
+
+        // Merged samples are evicted together with the slot they were merged into.
+        a.advance(2);
+        assert_eq!(a.total(), 1);
+    }
+
+    #[test]
+    fn test_merge_equal_scales() {
+        // Equal scales at different addresses are compatible.
+        static OTHER_SCALE: LazyLock<LogScale3> = LazyLock::new(LogScale3::new);
+
+        let mut a: Histogram = Histogram::new();
+        let mut b: Histogram = Histogram::with_log_scale(&OTHER_SCALE, 1);
+        b.record(5);
+
+        assert_eq!(a.merge(&b), Ok(()));
+        assert_eq!(a.total(), 1);
+    }
+
+    #[test]
+    fn test_subtract() {
+        let mut a: Histogram = Histogram::new();
+        let mut b: Histogram = Histogram::new();
+
+        for v in [1, 10, 10, 100] {
+            a.record(v);
+        }
+        b.record(10);
+
+        a -= &b;
+        assert_eq!(a.total(), 3);
+        assert_eq!(a.get_bucket(LogScale3::calculate_bucket_uncached(10)), 1);
+        assert_eq!(a.current_slot().buckets.to_dense(), a.aggregate_buckets);
+    }
+
+    #[test]
+    fn test_subtract_underflow_leaves_unchanged() {
+        let mut a: Histogram<u64> = Histogram::with_slots(2);
+        a.record(1);
+        a.record(10);
+        a.advance(1);
+        a.record(1);
+
+        // Bucket for 10 is only in the old slot, not in the current one.
+        let mut b: Histogram = Histogram::new();
+        b.record(1);
+        b.record(10);
+
+        let before = a.clone();
+        assert_eq!(
+            a.subtract(&b),
+            Err(HistogramError::Underflow {
+                bucket: LogScale3::calculate_bucket_uncached(10)
+            })
+        );
+        assert_eq!(a, before);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot subtract histograms: histogram subtraction underflow")]
+    fn test_sub_assign_underflow_panics() {
+        let mut a: Histogram = Histogram::new();
+        let mut b: Histogram = Histogram::new();
+        b.record(1);
+
+        a -= &b;
+    }
+
+    #[test]
+    fn test_difference() {
+        let mut hist: Histogram<u64> = Histogram::with_slots(3);
+        hist.record(1);
+        hist.record(100);
+
+        let previous = hist.clone();
+
+        hist.advance(1);
+        hist.record(100);
+        hist.record(1000);
+
+        let delta = hist.difference(&previous).unwrap();
+        assert_eq!(delta.active_slot_count(), 1);
+        assert_eq!(delta.total(), 2);
+        assert_eq!(delta.get_bucket(1), 0);
+        assert_eq!(
+            delta.get_bucket(LogScale3::calculate_bucket_uncached(100)),
+            1
+        );
+        assert_eq!(
+            delta.get_bucket(LogScale3::calculate_bucket_uncached(1000)),
+            1
+        );
+
+        assert_eq!(
+            previous.difference(&hist).unwrap_err(),
+            HistogramError::Underflow {
+                bucket: LogScale3::calculate_bucket_uncached(100)
+            }
+        );
+    }
 }
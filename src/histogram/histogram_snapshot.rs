@@ -0,0 +1,108 @@
+use super::bucket::Bucket;
+use super::bucket::non_empty_buckets;
+use super::bucket_scale::HistogramScale;
+use super::exemplar::Exemplar;
+use super::histogram::Histogram;
+use super::histogram::bucket_percentile;
+use super::histogram::bucket_percentile_stats;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+use super::quantiles::Quantiles;
+use super::quantiles::bucket_quantiles;
+use super::summary::Summary;
+
+/// An immutable copy of the aggregate of a histogram, taken when an interval is reported.
+///
+/// Returned by [`Histogram::take_snapshot()`] and
+/// [`AtomicHistogram::take_snapshot()`](super::AtomicHistogram::take_snapshot), which reset
+/// the histogram for the next interval. The snapshot can be cloned and handed to several
+/// reporters, queried for percentiles, or turned back into a histogram with
+/// [`into_histogram()`](Self::into_histogram), e.g. to merge intervals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot<const WIDTH: usize = 3> {
+    scale: HistogramScale<WIDTH>,
+    buckets: Vec<u64>,
+    summary: Summary,
+    exemplars: Vec<Exemplar>,
+}
+
+impl<const WIDTH: usize> HistogramSnapshot<WIDTH> {
+    pub(crate) fn new(
+        scale: HistogramScale<WIDTH>,
+        buckets: Vec<u64>,
+        summary: Summary,
+        exemplars: Vec<Exemplar>,
+    ) -> Self {
+        Self {
+            scale,
+            buckets,
+            summary,
+            exemplars,
+        }
+    }
+
+    /// Returns the count of each bucket, indexed like the scale's buckets.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Returns the latest exemplar of each bucket, in ascending order of value.
+    pub fn exemplars(&self) -> &[Exemplar] {
+        &self.exemplars
+    }
+
+    /// Returns the non-empty buckets, in ascending order of value.
+    pub fn non_empty_buckets(&self) -> impl DoubleEndedIterator<Item = Bucket> + '_ {
+        non_empty_buckets(&self.scale, &self.buckets)
+    }
+
+    /// Returns the number of values in the snapshot.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns the exact min, max, sum and sum of squares of the values.
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    /// Calculates the value at the given percentile. See [`Histogram::percentile()`].
+    pub fn percentile(&self, p: f64) -> u64 {
+        self.percentile_with_mode(p, PercentileMode::LowerBound)
+    }
+
+    /// Calculates the value at the given percentile, estimated within its bucket according to
+    /// `mode`.
+    pub fn percentile_with_mode(&self, p: f64, mode: PercentileMode) -> u64 {
+        bucket_percentile(&self.scale, &self.buckets, self.total(), p, mode)
+    }
+
+    /// Returns common percentile statistics: samples, P0.1, P1, P5, P10, P50, P90, P99, P99.9.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        self.percentile_stats_with_mode(PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics, estimated within their buckets according to
+    /// `mode`.
+    pub fn percentile_stats_with_mode(&self, mode: PercentileMode) -> PercentileStats {
+        bucket_percentile_stats(&self.scale, &self.buckets, mode)
+    }
+
+    /// Returns the values at each of the quantiles `qs`. See [`Histogram::quantiles()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a quantile is not in `[0, 1]`.
+    pub fn quantiles(&self, qs: &[f64]) -> Quantiles {
+        let values = bucket_quantiles(&self.scale, &self.buckets, qs, PercentileMode::LowerBound);
+        let summary = &self.summary;
+        Quantiles::from_values(summary.count(), qs, values, summary.min(), summary.max())
+    }
+
+    /// Converts the snapshot into a single-slot histogram holding its values.
+    ///
+    /// The exemplars are dropped; use [`exemplars()`](Self::exemplars) first to keep them.
+    pub fn into_histogram<T>(self) -> Histogram<T, WIDTH> {
+        Histogram::from_buckets(self.scale, self.buckets, self.summary)
+    }
+}
@@ -0,0 +1,91 @@
+use super::bucket_scale::BucketScale;
+
+/// A scale of equally wide buckets, for small bounded values such as queue depths.
+///
+/// Bucket `i` holds the values `[i * bucket_width, (i + 1) * bucket_width - 1]`, except the
+/// last bucket, which also holds every larger value.
+///
+/// ```
+/// use databend_base::histogram::BucketScale;
+/// use databend_base::histogram::LinearScale;
+///
+/// // Queue depths 0 to 63 each get a bucket, deeper queues share the last one.
+/// let scale = LinearScale::new(1, 65);
+/// assert_eq!(scale.calculate_bucket(17), 17);
+/// assert_eq!(scale.calculate_bucket(1000), 64);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinearScale {
+    bucket_width: u64,
+    num_buckets: usize,
+}
+
+impl LinearScale {
+    /// Creates `num_buckets` buckets of `bucket_width` values each, starting at 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_width` or `num_buckets` is 0, or if the last bucket would start
+    /// beyond `u64::MAX`.
+    pub fn new(bucket_width: u64, num_buckets: usize) -> Self {
+        assert!(bucket_width > 0, "bucket_width must be at least 1");
+        assert!(num_buckets > 0, "num_buckets must be at least 1");
+        assert!(
+            bucket_width.checked_mul(num_buckets as u64 - 1).is_some(),
+            "last bucket starts beyond u64::MAX"
+        );
+        Self {
+            bucket_width,
+            num_buckets,
+        }
+    }
+
+    /// Returns the number of values in each bucket but the last.
+    pub fn bucket_width(&self) -> u64 {
+        self.bucket_width
+    }
+}
+
+impl BucketScale for LinearScale {
+    fn num_buckets(&self) -> usize {
+        self.num_buckets
+    }
+
+    fn bucket_min_value(&self, bucket: usize) -> u64 {
+        bucket as u64 * self.bucket_width
+    }
+
+    fn calculate_bucket(&self, value: u64) -> usize {
+        (value / self.bucket_width).min(self.num_buckets as u64 - 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        let scale = LinearScale::new(10, 3);
+        assert_eq!(scale.num_buckets(), 3);
+        assert_eq!(scale.bucket_width(), 10);
+        assert_eq!(scale.calculate_bucket(0), 0);
+        assert_eq!(scale.calculate_bucket(9), 0);
+        assert_eq!(scale.calculate_bucket(10), 1);
+        assert_eq!(scale.calculate_bucket(20), 2);
+        assert_eq!(scale.calculate_bucket(u64::MAX), 2);
+        assert_eq!(scale.bucket_min_value(1), 10);
+        assert_eq!(scale.bucket_max_value(1), 19);
+        assert_eq!(scale.bucket_max_value(2), u64::MAX);
+
+        let single = LinearScale::new(u64::MAX, 1);
+        assert_eq!(single.calculate_bucket(u64::MAX), 0);
+        assert_eq!(single.bucket_max_value(0), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "last bucket starts beyond u64::MAX")]
+    fn test_overflow_panics() {
+        LinearScale::new(1 << 63, 3);
+    }
+}
@@ -1,4 +1,5 @@
-use std::sync::LazyLock;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
 
 use super::log_scale_config::LogScaleConfig;
 
@@ -8,27 +9,48 @@ use super::log_scale_config::LogScaleConfig;
 /// - Value → bucket index (with small-value cache)
 /// - Bucket index → minimum value
 ///
+/// The lookup tables are fixed-size arrays built at compile time for WIDTH 1 through 8 (see
+/// [`SharedLogScale`]), and built at runtime once per WIDTH for the others. Either way they
+/// live for the whole program, so a `LogScale` is just a pair of references to static data
+/// and is cheap to copy.
+///
 /// Use the shared [`LOG_SCALE`] instance for WIDTH=3 (default configuration).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LogScale<const WIDTH: usize> {
     /// Minimum value represented by each bucket index.
-    bucket_min_values: Vec<u64>,
+    bucket_min_values: &'static [u64],
     /// Cached bucket indices for small values (0-4095).
-    small_value_buckets: Vec<u8>,
+    small_value_buckets: SmallValueBuckets,
+}
+
+/// Cached bucket indices for small values, stored in the narrowest type that fits.
+///
+/// Bucket indices of values below 4096 fit in `u8` up to WIDTH=6. Wider configurations
+/// need `u16`, which holds any index below 4096.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SmallValueBuckets {
+    U8(&'static [u8; 4096]),
+    U16(&'static [u16; 4096]),
+}
+
+impl SmallValueBuckets {
+    #[inline]
+    fn get(&self, value: usize) -> usize {
+        match self {
+            Self::U8(c) => c[value] as usize,
+            Self::U16(c) => c[value] as usize,
+        }
+    }
 }
 
 impl<const WIDTH: usize> LogScale<WIDTH> {
     /// Creates a new LogScale for the given WIDTH configuration.
+    ///
+    /// Uses the tables of the shared instance if WIDTH has one. Otherwise the tables are built
+    /// on the first call for this WIDTH and reused by later calls.
     pub fn new() -> Self {
-        // Build bucket_min_values table
-        let bucket_min_values: Vec<u64> =
-            (0..LogScaleConfig::<WIDTH>::BUCKETS).map(Self::compute_bucket_min_value).collect();
-
-        // Build small_value_buckets cache
-        let small_value_buckets: Vec<u8> = (0..LogScaleConfig::<WIDTH>::SMALL_VALUE_CACHE_SIZE)
-            .map(|v| Self::calculate_bucket_uncached(v as u64) as u8)
-            .collect();
-
+        let (bucket_min_values, small_value_buckets) =
+            shared_tables(WIDTH).unwrap_or_else(Self::runtime_tables);
         Self {
             bucket_min_values,
             small_value_buckets,
@@ -47,11 +69,22 @@ impl<const WIDTH: usize> LogScale<WIDTH> {
         self.bucket_min_values[bucket]
     }
 
+    /// Returns the maximum value (inclusive) for the given bucket index.
+    ///
+    /// This is one less than the minimum value of the next bucket, or `u64::MAX` for the last one.
+    #[inline]
+    pub fn bucket_max_value(&self, bucket: usize) -> u64 {
+        match self.bucket_min_values.get(bucket + 1) {
+            Some(next_min) => next_min - 1,
+            None => u64::MAX,
+        }
+    }
+
     /// Calculates bucket index for a value, using cache for small values.
     #[inline]
     pub fn calculate_bucket(&self, value: u64) -> usize {
-        if value < self.small_value_buckets.len() as u64 {
-            return self.small_value_buckets[value as usize] as usize;
+        if value < LogScaleConfig::<WIDTH>::SMALL_VALUE_CACHE_SIZE as u64 {
+            return self.small_value_buckets.get(value as usize);
         }
         Self::calculate_bucket_uncached(value)
     }
@@ -65,7 +98,7 @@ impl<const WIDTH: usize> LogScale<WIDTH> {
     ///    - Determine which group of GROUP_SIZE buckets
     ///    - Extract offset within that group using the bits after MSB
     ///    - Bucket index = base of this group + offset within group
-    pub fn calculate_bucket_uncached(value: u64) -> usize {
+    pub const fn calculate_bucket_uncached(value: u64) -> usize {
         if value < LogScaleConfig::<WIDTH>::GROUP_SIZE as u64 {
             return value as usize;
         }
@@ -78,7 +111,7 @@ impl<const WIDTH: usize> LogScale<WIDTH> {
     }
 
     /// Computes the minimum value for a bucket index.
-    fn compute_bucket_min_value(bucket: usize) -> u64 {
+    const fn compute_bucket_min_value(bucket: usize) -> u64 {
         if bucket < LogScaleConfig::<WIDTH>::GROUP_SIZE {
             return bucket as u64;
         }
@@ -88,6 +121,78 @@ impl<const WIDTH: usize> LogScale<WIDTH> {
             (bucket - LogScaleConfig::<WIDTH>::GROUP_SIZE) % LogScaleConfig::<WIDTH>::GROUP_SIZE;
         ((offset_in_group | LogScaleConfig::<WIDTH>::GROUP_MSB_BIT) << group_index) as u64
     }
+
+    /// Returns the tables of a WIDTH without a shared instance, building them on first use.
+    ///
+    /// The tables are leaked, once per WIDTH, to be shared like the static ones.
+    fn runtime_tables() -> Tables {
+        static TABLES: Mutex<BTreeMap<usize, Tables>> = Mutex::new(BTreeMap::new());
+
+        let mut tables = TABLES.lock().unwrap_or_else(|e| e.into_inner());
+        *tables.entry(WIDTH).or_insert_with(|| {
+            let bucket_min_values: Vec<u64> =
+                (0..LogScaleConfig::<WIDTH>::BUCKETS).map(Self::compute_bucket_min_value).collect();
+
+            let values = 0..LogScaleConfig::<WIDTH>::SMALL_VALUE_CACHE_SIZE as u64;
+            let small_value_buckets =
+                if Self::calculate_bucket_uncached(values.end - 1) <= u8::MAX as usize {
+                    let cache: Vec<u8> =
+                        values.map(|v| Self::calculate_bucket_uncached(v) as u8).collect();
+                    SmallValueBuckets::U8(Box::leak(cache.into_boxed_slice().try_into().unwrap()))
+                } else {
+                    let cache: Vec<u16> =
+                        values.map(|v| Self::calculate_bucket_uncached(v) as u16).collect();
+                    SmallValueBuckets::U16(Box::leak(cache.into_boxed_slice().try_into().unwrap()))
+                };
+
+            (bucket_min_values.leak(), small_value_buckets)
+        })
+    }
+
+    /// Builds the bucket_min_values table. `N` must be `LogScaleConfig::<WIDTH>::BUCKETS`.
+    const fn build_bucket_min_values<const N: usize>() -> [u64; N] {
+        assert!(N == LogScaleConfig::<WIDTH>::BUCKETS);
+
+        let mut table = [0; N];
+        let mut bucket = 0;
+        while bucket < N {
+            table[bucket] = Self::compute_bucket_min_value(bucket);
+            bucket += 1;
+        }
+        table
+    }
+
+    /// Builds the small_value_buckets cache with `u16` indices.
+    const fn build_small_value_buckets_u16<const N: usize>() -> [u16; N] {
+        assert!(N == LogScaleConfig::<WIDTH>::SMALL_VALUE_CACHE_SIZE);
+
+        let mut table = [0; N];
+        let mut value = 0;
+        while value < N {
+            table[value] = Self::calculate_bucket_uncached(value as u64) as u16;
+            value += 1;
+        }
+        table
+    }
+
+    /// Builds the small_value_buckets cache with `u8` indices.
+    ///
+    /// Fails to compile if an index does not fit in `u8`.
+    const fn build_small_value_buckets_u8<const N: usize>() -> [u8; N] {
+        let wide = Self::build_small_value_buckets_u16::<N>();
+
+        let mut table = [0; N];
+        let mut value = 0;
+        while value < N {
+            assert!(
+                wide[value] <= u8::MAX as u16,
+                "bucket index does not fit in u8"
+            );
+            table[value] = wide[value] as u8;
+            value += 1;
+        }
+        table
+    }
 }
 
 impl<const WIDTH: usize> Default for LogScale<WIDTH> {
@@ -96,11 +201,79 @@ impl<const WIDTH: usize> Default for LogScale<WIDTH> {
     }
 }
 
+/// Builds a `LogScale<$width>` in const context, with `$index` (`U8` or `U16`) cache entries.
+macro_rules! const_log_scale {
+    ($width:literal, U8) => {
+        const_log_scale!($width, U8, build_small_value_buckets_u8, u8)
+    };
+    ($width:literal, U16) => {
+        const_log_scale!($width, U16, build_small_value_buckets_u16, u16)
+    };
+    ($width:literal, $variant:ident, $build:ident, $ty:ty) => {{
+        const BUCKET_MIN_VALUES: [u64; LogScaleConfig::<$width>::BUCKETS] =
+            LogScale::<$width>::build_bucket_min_values();
+        const SMALL_VALUE_BUCKETS: [$ty; LogScaleConfig::<$width>::SMALL_VALUE_CACHE_SIZE] =
+            LogScale::<$width>::$build();
+
+        LogScale::<$width> {
+            bucket_min_values: &BUCKET_MIN_VALUES,
+            small_value_buckets: SmallValueBuckets::$variant(&SMALL_VALUE_BUCKETS),
+        }
+    }};
+}
+
 /// Default log scale with WIDTH=3 (252 buckets, ~12.5% max error).
 pub type LogScale3 = LogScale<3>;
 
 /// Shared LogScale instance for WIDTH=3 (default configuration).
-pub static LOG_SCALE: LazyLock<LogScale3> = LazyLock::new(LogScale3::new);
+pub static LOG_SCALE: LogScale3 = const_log_scale!(3, U8);
+
+/// A [`LogScale`] WIDTH that has a shared instance with tables built at compile time.
+///
+/// Implemented for WIDTH 1 through 8. [`LogScale::new()`] uses the tables of the shared
+/// instance when there is one; other widths work as well, with tables built at runtime.
+pub trait SharedLogScale: 'static {
+    /// Returns the shared instance for this WIDTH.
+    fn shared() -> &'static Self;
+}
+
+impl SharedLogScale for LogScale3 {
+    fn shared() -> &'static Self {
+        &LOG_SCALE
+    }
+}
+
+/// The lookup tables of a [`LogScale`].
+type Tables = (&'static [u64], SmallValueBuckets);
+
+macro_rules! impl_shared_log_scale {
+    ($($width:literal => $index:ident),*) => {
+        $(
+            impl SharedLogScale for LogScale<$width> {
+                fn shared() -> &'static Self {
+                    static SHARED: LogScale<$width> = const_log_scale!($width, $index);
+                    &SHARED
+                }
+            }
+        )*
+
+        /// Returns the tables of the shared instance of `width`, if it has one.
+        fn shared_tables(width: usize) -> Option<Tables> {
+            match width {
+                3 => Some((LOG_SCALE.bucket_min_values, LOG_SCALE.small_value_buckets)),
+                $(
+                    $width => {
+                        let shared = LogScale::<$width>::shared();
+                        Some((shared.bucket_min_values, shared.small_value_buckets))
+                    }
+                )*
+                _ => None,
+            }
+        }
+    };
+}
+
+impl_shared_log_scale!(1 => U8, 2 => U8, 4 => U8, 5 => U8, 6 => U8, 7 => U16, 8 => U16);
 
 #[cfg(test)]
 mod tests {
@@ -188,6 +361,21 @@ mod tests {
         assert_eq!(LOG_SCALE.bucket_min_value(15), 28);
     }
 
+    #[test]
+    fn test_bucket_max_value() {
+        assert_eq!(LOG_SCALE.bucket_max_value(0), 0);
+        assert_eq!(LOG_SCALE.bucket_max_value(7), 7);
+        assert_eq!(LOG_SCALE.bucket_max_value(8), 9);
+        assert_eq!(LOG_SCALE.bucket_max_value(15), 31);
+        assert_eq!(LOG_SCALE.bucket_max_value(250), (7 << 61) - 1);
+        assert_eq!(LOG_SCALE.bucket_max_value(251), u64::MAX);
+
+        for bucket in 0..LOG_SCALE.num_buckets() {
+            let max = LOG_SCALE.bucket_max_value(bucket);
+            assert_eq!(LogScale3::calculate_bucket_uncached(max), bucket);
+        }
+    }
+
     #[test]
     fn test_cached_bucket_matches_uncached() {
         // Sample values across cache range to verify cache correctness
@@ -217,6 +405,130 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shared_log_scale() {
+        assert!(std::ptr::eq(LogScale3::shared(), &LOG_SCALE));
+        assert!(std::ptr::eq(
+            LogScale::<4>::shared(),
+            LogScale::<4>::shared()
+        ));
+        assert_eq!(
+            LogScale::<4>::shared().num_buckets(),
+            LogScaleConfig::<4>::BUCKETS
+        );
+    }
+
+    fn check_width<const WIDTH: usize>()
+    where LogScale<WIDTH>: SharedLogScale {
+        let scale = LogScale::<WIDTH>::shared();
+        assert_eq!(scale.num_buckets(), LogScaleConfig::<WIDTH>::BUCKETS);
+        assert_eq!(
+            LogScale::<WIDTH>::calculate_bucket_uncached(u64::MAX),
+            LogScaleConfig::<WIDTH>::BUCKETS - 1
+        );
+
+        for v in 0..LogScaleConfig::<WIDTH>::SMALL_VALUE_CACHE_SIZE as u64 {
+            let bucket = scale.calculate_bucket(v);
+            assert_eq!(
+                bucket,
+                LogScale::<WIDTH>::calculate_bucket_uncached(v),
+                "value {}",
+                v
+            );
+            assert!(scale.bucket_min_value(bucket) <= v && v <= scale.bucket_max_value(bucket));
+        }
+    }
+
+    #[test]
+    fn test_supported_widths() {
+        check_width::<1>();
+        check_width::<2>();
+        check_width::<3>();
+        check_width::<4>();
+        check_width::<5>();
+        check_width::<6>();
+        check_width::<7>();
+        check_width::<8>();
+    }
+
+    fn check_const_tables<const WIDTH: usize>()
+    where LogScale<WIDTH>: SharedLogScale {
+        let scale = LogScale::<WIDTH>::shared();
+        assert_eq!(*scale, LogScale::<WIDTH>::new());
+
+        for bucket in 0..scale.num_buckets() {
+            assert_eq!(
+                scale.bucket_min_value(bucket),
+                LogScale::<WIDTH>::compute_bucket_min_value(bucket)
+            );
+        }
+    }
+
+    #[test]
+    fn test_const_tables() {
+        check_const_tables::<1>();
+        check_const_tables::<3>();
+        check_const_tables::<5>();
+        check_const_tables::<8>();
+
+        // Tables are static: every instance of a WIDTH points to the same data.
+        let a = LogScale3::new();
+        let b = LogScale3::default();
+        assert!(std::ptr::eq(a.bucket_min_values, b.bucket_min_values));
+        assert!(std::ptr::eq(
+            a.bucket_min_values,
+            LOG_SCALE.bucket_min_values
+        ));
+    }
+
+    #[test]
+    fn test_runtime_tables() {
+        let scale = LogScale::<10>::new();
+        assert_eq!(scale.num_buckets(), LogScaleConfig::<10>::BUCKETS);
+        for bucket in 0..scale.num_buckets() {
+            assert_eq!(
+                scale.bucket_min_value(bucket),
+                LogScale::<10>::compute_bucket_min_value(bucket)
+            );
+        }
+        for v in (0..5000).chain([1 << 40, u64::MAX]) {
+            assert_eq!(
+                scale.calculate_bucket(v),
+                LogScale::<10>::calculate_bucket_uncached(v)
+            );
+        }
+        assert!(matches!(
+            scale.small_value_buckets,
+            SmallValueBuckets::U16(_)
+        ));
+
+        // Built once per WIDTH.
+        assert!(std::ptr::eq(
+            scale.bucket_min_values,
+            LogScale::<10>::default().bucket_min_values
+        ));
+        assert!(matches!(
+            LogScale::<9>::new().small_value_buckets,
+            SmallValueBuckets::U16(_)
+        ));
+    }
+
+    #[test]
+    fn test_small_value_cache_type() {
+        assert!(matches!(
+            LogScale::<6>::shared().small_value_buckets,
+            SmallValueBuckets::U8(_)
+        ));
+        assert!(matches!(
+            LogScale::<7>::shared().small_value_buckets,
+            SmallValueBuckets::U16(_)
+        ));
+
+        // The largest cached index of WIDTH=7 does not fit in u8.
+        assert_eq!(LogScale::<7>::calculate_bucket_uncached(4095), 447);
+        assert_eq!(LogScale::<7>::shared().calculate_bucket(4095), 447);
+    }
+
     #[test]
     fn test_cached_bucket_large_values() {
         // Values beyond cache should still work correctly
@@ -2,7 +2,8 @@
 ///
 /// The `WIDTH` parameter determines bucket granularity:
 /// - WIDTH=3: 4 buckets per group, 252 total buckets, ~12.5% max error
-/// - WIDTH=4: 8 buckets per group, 504 total buckets, ~6.25% max error
+/// - WIDTH=4: 8 buckets per group, 496 total buckets, ~6.25% max error
+/// - WIDTH=8: 128 buckets per group, 7424 total buckets, ~0.78% max error
 ///
 /// All derived constants are computed at compile time from `WIDTH`.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,7 +40,8 @@ impl<const WIDTH: usize> LogScaleConfig<WIDTH> {
 
     /// Cache size for small value bucket lookups.
     ///
-    /// Values 0-4095 map to bucket indices 0-44, fitting in u8.
+    /// Values 0-4095 map to bucket indices 0-44 for WIDTH=3. Indices fit in u8 up to WIDTH=6 and
+    /// always fit in u16.
     pub const SMALL_VALUE_CACHE_SIZE: usize = 4096;
 }
 
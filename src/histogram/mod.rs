@@ -1,14 +1,78 @@
+mod atomic_histogram;
+mod bucket;
+mod bucket_scale;
+mod clock;
+mod codec;
+mod compare;
+mod decaying_histogram;
+mod error;
+mod exemplar;
+mod explicit_bounds_scale;
+mod exponential;
+mod float_histogram;
 #[allow(clippy::module_inception)]
 mod histogram;
+mod histogram_snapshot;
+mod linear_scale;
 mod log_scale;
 mod log_scale_config;
+mod percentile_mode;
 mod percentile_stats;
+mod prometheus;
+mod quantiles;
+mod render;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod signed_histogram;
 mod slot;
+mod slot_buckets;
+mod slot_snapshot;
+mod slot_view;
+mod summary;
+mod windowed_histogram;
 
+pub use atomic_histogram::AtomicHistogram;
+pub use bucket::Bucket;
+pub use bucket_scale::BucketScale;
+pub use clock::Clock;
+pub use clock::ManualClock;
+pub use clock::SystemClock;
+pub use codec::DecodeError;
+pub use codec::MAX_DECODE_CAPACITY;
+pub use compare::Comparison;
+pub use compare::QuantileDelta;
+pub use compare::RegressionCheck;
+pub use compare::Verdict;
+pub use compare::Violation;
+pub use decaying_histogram::DecayingHistogram;
+pub use error::HistogramError;
+pub use exemplar::Exemplar;
+pub use explicit_bounds_scale::ExplicitBoundsScale;
+pub use exponential::DEFAULT_EXPONENTIAL_SCALE;
+pub use exponential::ExponentialBuckets;
+pub use exponential::ExponentialHistogram;
+pub use exponential::MAX_EXPONENTIAL_BUCKETS;
+pub use exponential::MAX_EXPONENTIAL_SCALE;
+pub use exponential::MIN_EXPONENTIAL_SCALE;
+pub use float_histogram::FloatHistogram;
 pub use histogram::Histogram;
+pub use histogram_snapshot::HistogramSnapshot;
+pub use linear_scale::LinearScale;
 pub use log_scale::LOG_SCALE;
 pub use log_scale::LogScale;
 pub use log_scale::LogScale3;
+pub use log_scale::SharedLogScale;
 pub use log_scale_config::DefaultLogScaleConfig;
 pub use log_scale_config::LogScaleConfig;
+pub use percentile_mode::PercentileMode;
 pub use percentile_stats::PercentileStats;
+pub use prometheus::PrometheusExporter;
+pub use quantiles::QuantileValue;
+pub use quantiles::Quantiles;
+pub use render::TextRenderer;
+pub use render::Unit;
+pub use signed_histogram::SignedHistogram;
+pub use slot_snapshot::SlotSnapshot;
+pub use slot_view::SlotView;
+pub use summary::Summary;
+pub use windowed_histogram::WindowedHistogram;
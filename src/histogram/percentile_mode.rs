@@ -0,0 +1,122 @@
+/// How a percentile is estimated from the bucket that contains it.
+///
+/// A histogram only knows that the value at a given rank lies in some bucket `[min, max]`. The
+/// mode selects which value of that range is reported. With `GROUP_SIZE = 2^(WIDTH - 1)`
+/// buckets per power of 2 (4 for the default WIDTH=3), the relative error of the reported value
+/// against the exact one is bounded as follows; values below `2^WIDTH` have single-value
+/// buckets and are always exact.
+///
+/// | Mode          | Bias              | Max relative error     | WIDTH=3 |
+/// |---------------|-------------------|------------------------|---------|
+/// | `LowerBound`  | never above exact | `1 / (GROUP_SIZE + 1)` | 20%     |
+/// | `UpperBound`  | never below exact | `1 / GROUP_SIZE`       | 25%     |
+/// | `Midpoint`    | none              | `1 / (2 * GROUP_SIZE)` | 12.5%   |
+/// | `Interpolate` | none              | `1 / GROUP_SIZE`       | 25%     |
+///
+/// `Interpolate` assumes the values in a bucket are spread uniformly, and places the value of
+/// rank `k` among the `n` values of its bucket at `min + (k - 0.5) / n * (max - min + 1)`. Its
+/// worst case is a bucket whose values are all at one edge, but for smooth distributions it is
+/// usually the most accurate mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PercentileMode {
+    /// The minimum value of the bucket. This is the historical behavior.
+    #[default]
+    LowerBound,
+    /// The maximum value of the bucket.
+    UpperBound,
+    /// The middle of the bucket, rounded down.
+    Midpoint,
+    /// Linear interpolation inside the bucket, based on the rank within the bucket.
+    Interpolate,
+}
+
+impl PercentileMode {
+    /// Returns the mode that gives the same bias on negated values: `LowerBound` and
+    /// `UpperBound` are swapped.
+    pub(crate) fn mirrored(self) -> Self {
+        match self {
+            PercentileMode::LowerBound => PercentileMode::UpperBound,
+            PercentileMode::UpperBound => PercentileMode::LowerBound,
+            other => other,
+        }
+    }
+
+    /// Estimates the value of rank `rank` (1-based) among the `count` values of the bucket
+    /// `[min, max]`.
+    pub(crate) fn estimate(self, min: u64, max: u64, rank: u64, count: u64) -> u64 {
+        debug_assert!(min <= max && 1 <= rank && rank <= count);
+
+        match self {
+            PercentileMode::LowerBound => min,
+            PercentileMode::UpperBound => max,
+            PercentileMode::Midpoint => min + (max - min) / 2,
+            PercentileMode::Interpolate => {
+                let width = (max - min) as f64 + 1.0;
+                let offset = (width * (rank as f64 - 0.5) / count as f64) as u64;
+                min.saturating_add(offset).min(max)
+            }
+        }
+    }
+
+    /// Estimates the value at `fraction`, in `(0, 1]`, of the weight of the bucket
+    /// `[min, max]`, for buckets holding weights rather than counts.
+    pub(crate) fn estimate_weighted(self, min: u64, max: u64, fraction: f64) -> u64 {
+        match self {
+            PercentileMode::Interpolate => {
+                let width = (max - min) as f64 + 1.0;
+                let offset = (width * fraction) as u64;
+                min.saturating_add(offset).min(max)
+            }
+            other => other.estimate(min, max, 1, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(PercentileMode::default(), PercentileMode::LowerBound);
+
+        assert_eq!(PercentileMode::LowerBound.estimate(96, 111, 1, 4), 96);
+        assert_eq!(PercentileMode::UpperBound.estimate(96, 111, 1, 4), 111);
+        assert_eq!(PercentileMode::Midpoint.estimate(96, 111, 1, 4), 103);
+
+        // 4 values spread over 16: at offsets 2, 6, 10 and 14.
+        let interpolated: Vec<u64> =
+            (1..=4).map(|k| PercentileMode::Interpolate.estimate(96, 111, k, 4)).collect();
+        assert_eq!(interpolated, vec![98, 102, 106, 110]);
+    }
+
+    #[test]
+    fn test_estimate_single_value_bucket() {
+        for mode in [
+            PercentileMode::LowerBound,
+            PercentileMode::UpperBound,
+            PercentileMode::Midpoint,
+            PercentileMode::Interpolate,
+        ] {
+            assert_eq!(mode.estimate(7, 7, 3, 3), 7);
+        }
+    }
+
+    #[test]
+    fn test_estimate_last_bucket() {
+        let min = 14 << 60;
+        let max = u64::MAX;
+        assert_eq!(
+            PercentileMode::Midpoint.estimate(min, max, 1, 1),
+            min + (1 << 60) - 1
+        );
+        assert_eq!(
+            PercentileMode::Interpolate.estimate(min, max, 1, 1),
+            min + (1 << 60)
+        );
+        assert_eq!(
+            PercentileMode::Interpolate.estimate(min, max, 2, 2),
+            min + 3 * (1 << 59)
+        );
+    }
+}
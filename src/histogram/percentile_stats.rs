@@ -1,29 +1,55 @@
 use std::fmt;
 
 /// Percentile statistics for a histogram.
+///
+/// Values are `u64`, or `i64` and `f64` for [`SignedHistogram`](super::SignedHistogram) and
+/// [`FloatHistogram`](super::FloatHistogram).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct PercentileStats {
+pub struct PercentileStats<V = u64> {
     /// Number of samples recorded
     pub samples: u64,
     /// 0.1th percentile (99.9% of values >= this)
-    pub p0_1: u64,
+    pub p0_1: V,
     /// 1st percentile (99% of values >= this)
-    pub p1: u64,
+    pub p1: V,
     /// 5th percentile (95% of values >= this)
-    pub p5: u64,
+    pub p5: V,
     /// 10th percentile (90% of values >= this)
-    pub p10: u64,
+    pub p10: V,
     /// 50th percentile (median)
-    pub p50: u64,
+    pub p50: V,
     /// 90th percentile
-    pub p90: u64,
+    pub p90: V,
     /// 99th percentile
-    pub p99: u64,
+    pub p99: V,
     /// 99.9th percentile
-    pub p99_9: u64,
+    pub p99_9: V,
 }
 
-impl fmt::Display for PercentileStats {
+impl PercentileStats {
+    /// The quantiles reported, in field order.
+    pub(crate) const QUANTILES: [f64; 8] = [0.001, 0.01, 0.05, 0.10, 0.50, 0.90, 0.99, 0.999];
+}
+
+impl<V: Copy> PercentileStats<V> {
+    /// Builds the statistics from the values at each of
+    /// [`QUANTILES`](PercentileStats::QUANTILES).
+    pub(crate) fn from_values(samples: u64, v: &[V]) -> Self {
+        Self {
+            samples,
+            p0_1: v[0],
+            p1: v[1],
+            p5: v[2],
+            p10: v[3],
+            p50: v[4],
+            p90: v[5],
+            p99: v[6],
+            p99_9: v[7],
+        }
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for PercentileStats<V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
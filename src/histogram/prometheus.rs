@@ -0,0 +1,403 @@
+use std::fmt;
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+use super::bucket_scale::BucketScale;
+use super::exemplar::Exemplar;
+use super::histogram::Histogram;
+
+/// Renders a [`Histogram`] in the Prometheus text exposition format, as a `histogram` metric.
+///
+/// The output contains cumulative `<name>_bucket{le="..."}` lines, followed by `<name>_sum` and
+/// `<name>_count`:
+///
+/// ```text
+/// # TYPE latency_us histogram
+/// latency_us_bucket{node="1",le="0"} 0
+/// latency_us_bucket{node="1",le="1"} 0
+/// latency_us_bucket{node="1",le="2"} 3
+/// ...
+/// latency_us_bucket{node="1",le="111"} 4
+/// latency_us_bucket{node="1",le="+Inf"} 4
+/// latency_us_sum{node="1"} 105
+/// latency_us_count{node="1"} 4
+/// ```
+///
+/// By default, one `le` line is emitted per log bucket, from the first one up to the highest
+/// non-empty one, where `le` is the bucket's inclusive maximum
+/// ([`LogScale::bucket_max_value`](super::LogScale::bucket_max_value)). As the counts are
+/// cumulative, the highest non-empty bucket never moves down, so a series never disappears
+/// from one scrape to the next. Use [`boundaries()`](Self::boundaries) to get a fixed, coarser
+/// set of `le` values, or [`sparse()`](Self::sparse) to emit non-empty buckets only.
+///
+/// `_sum` is the exact sum of the recorded values, see [`Summary`](super::Summary).
+///
+/// With [`exemplars()`](Self::exemplars), bucket lines carry the latest
+/// [`Exemplar`] of their range in the OpenMetrics syntax:
+///
+/// ```text
+/// latency_us_bucket{le="111"} 4 # {trace_id="abc"} 100 1700000000.123
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrometheusExporter {
+    name: String,
+    help: Option<String>,
+    labels: Vec<(String, String)>,
+    boundaries: Option<Vec<u64>>,
+    sparse: bool,
+    exemplar_label: Option<String>,
+}
+
+impl PrometheusExporter {
+    /// Creates an exporter for the metric `name`.
+    ///
+    /// `name` is written as is; it must be a valid Prometheus metric name.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            help: None,
+            labels: vec![],
+            boundaries: None,
+            sparse: false,
+            exemplar_label: None,
+        }
+    }
+
+    /// Sets the `# HELP` text of the metric.
+    pub fn help(mut self, help: impl ToString) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    /// Adds a label attached to every line of the metric.
+    ///
+    /// `key` is written as is; `value` is escaped.
+    pub fn label(mut self, key: impl ToString, value: impl ToString) -> Self {
+        self.labels.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Coarsens the output to the given `le` boundaries instead of one line per log bucket.
+    ///
+    /// A log bucket is counted under the smallest boundary that is `>=` its maximum value.
+    /// When a boundary falls inside a log bucket, that bucket is counted under the next
+    /// boundary, so a cumulative count never includes values above its `le`.
+    pub fn boundaries(mut self, boundaries: impl IntoIterator<Item = u64>) -> Self {
+        let mut b: Vec<u64> = boundaries.into_iter().collect();
+        b.sort_unstable();
+        b.dedup();
+        self.boundaries = Some(b);
+        self
+    }
+
+    /// Emits only the non-empty log buckets, instead of every log bucket up to the highest
+    /// non-empty one. Has no effect with [`boundaries()`](Self::boundaries).
+    ///
+    /// The set of `le` series then changes with the recorded values: a bucket that is empty
+    /// in one scrape is missing from it, which breaks `histogram_quantile()` over `rate()`.
+    /// Only use it when the output is read as a whole, not scraped as a time series.
+    pub fn sparse(mut self) -> Self {
+        self.sparse = true;
+        self
+    }
+
+    /// Appends exemplars to the bucket lines, with their id as the value of the label `label`,
+    /// e.g. `trace_id`.
+    ///
+    /// Exemplars are an OpenMetrics extension: only enable them for scrapers that accept the
+    /// OpenMetrics text format.
+    pub fn exemplars(mut self, label: impl ToString) -> Self {
+        self.exemplar_label = Some(label.to_string());
+        self
+    }
+
+    /// Renders the histogram aggregate as a Prometheus `histogram` metric.
+    pub fn render<T, const WIDTH: usize>(&self, hist: &Histogram<T, WIDTH>) -> String {
+        let mut out = String::new();
+        self.render_to(hist, &mut out).unwrap();
+        out
+    }
+
+    /// Renders the histogram aggregate into `out`. See [`render()`](Self::render).
+    pub fn render_to<T, const WIDTH: usize>(
+        &self,
+        hist: &Histogram<T, WIDTH>,
+        out: &mut impl Write,
+    ) -> fmt::Result {
+        let name = &self.name;
+        let log_scale = hist.scale();
+        let buckets = hist.aggregate_buckets();
+
+        if let Some(help) = &self.help {
+            writeln!(out, "# HELP {} {}", name, escape_help(help))?;
+        }
+        writeln!(out, "# TYPE {} histogram", name)?;
+
+        // Exemplar of each log bucket, if enabled.
+        let mut exemplars: Vec<Option<&Exemplar>> = vec![None; buckets.len()];
+        if self.exemplar_label.is_some() {
+            for (bucket, exemplar) in hist.bucket_exemplars() {
+                exemplars[bucket] = Some(exemplar);
+            }
+        }
+
+        let mut cumulative = 0u64;
+        let mut inf_exemplar = None;
+        match &self.boundaries {
+            None => {
+                let end = buckets.iter().rposition(|&c| c > 0).map_or(0, |b| b + 1);
+                for (bucket, &count) in buckets[..end].iter().enumerate() {
+                    if count == 0 && self.sparse {
+                        continue;
+                    }
+                    cumulative += count;
+                    let le = log_scale.bucket_max_value(bucket);
+                    self.write_bucket_line(out, &le.to_string(), cumulative, exemplars[bucket])?;
+                }
+            }
+            Some(boundaries) => {
+                let mut bucket = 0;
+                for &le in boundaries {
+                    let mut exemplar = None;
+                    while bucket < buckets.len() && log_scale.bucket_max_value(bucket) <= le {
+                        cumulative += buckets[bucket];
+                        exemplar = latest(exemplar, exemplars[bucket]);
+                        bucket += 1;
+                    }
+                    self.write_bucket_line(out, &le.to_string(), cumulative, exemplar)?;
+                }
+                inf_exemplar = exemplars[bucket..].iter().fold(None, |a, &b| latest(a, b));
+            }
+        }
+
+        let total = hist.total();
+        self.write_bucket_line(out, "+Inf", total, inf_exemplar)?;
+
+        let sum = hist.summary().sum();
+        writeln!(out, "{}_sum{} {}", name, self.label_set(None), sum)?;
+        writeln!(out, "{}_count{} {}", name, self.label_set(None), total)?;
+        Ok(())
+    }
+
+    fn write_bucket_line(
+        &self,
+        out: &mut impl Write,
+        le: &str,
+        count: u64,
+        exemplar: Option<&Exemplar>,
+    ) -> fmt::Result {
+        write!(
+            out,
+            "{}_bucket{} {}",
+            self.name,
+            self.label_set(Some(le)),
+            count
+        )?;
+        if let (Some(label), Some(exemplar)) = (&self.exemplar_label, exemplar) {
+            let timestamp = exemplar.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            write!(
+                out,
+                " # {{{}=\"{}\"}} {} {}.{:03}",
+                label,
+                escape_label_value(&exemplar.id),
+                exemplar.value,
+                timestamp.as_secs(),
+                timestamp.subsec_millis()
+            )?;
+        }
+        writeln!(out)
+    }
+
+    /// Formats the label set, with an optional trailing `le` label.
+    fn label_set(&self, le: Option<&str>) -> String {
+        let mut pairs: Vec<String> = self
+            .labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+            .collect();
+        if let Some(le) = le {
+            pairs.push(format!("le=\"{}\"", le));
+        }
+
+        if pairs.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", pairs.join(","))
+        }
+    }
+}
+
+/// Returns the more recent of two optional exemplars.
+fn latest<'a>(a: Option<&'a Exemplar>, b: Option<&'a Exemplar>) -> Option<&'a Exemplar> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.timestamp >= a.timestamp { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Escapes `\`, `"` and newline in a label value.
+fn escape_label_value(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Escapes `\` and newline in a help text.
+fn escape_help(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::histogram::LOG_SCALE;
+
+    fn sample_histogram() -> Histogram {
+        let mut hist: Histogram = Histogram::new();
+        for v in [1, 2, 2, 100, 5000] {
+            hist.record(v);
+        }
+        hist
+    }
+
+    #[test]
+    fn test_render_log_buckets() {
+        let out = PrometheusExporter::new("m").render(&sample_histogram());
+        let lines: Vec<&str> = out.lines().collect();
+
+        // Every log bucket up to the one of 5000, [4096, 5119], then +Inf.
+        let last = LOG_SCALE.calculate_bucket(5000);
+        assert_eq!(lines.len(), 1 + (last + 1) + 3);
+        assert_eq!(&lines[..5], [
+            "# TYPE m histogram",
+            r#"m_bucket{le="0"} 0"#,
+            r#"m_bucket{le="1"} 1"#,
+            r#"m_bucket{le="2"} 3"#,
+            r#"m_bucket{le="3"} 3"#,
+        ]);
+        assert!(lines.contains(&r#"m_bucket{le="95"} 3"#));
+        assert!(lines.contains(&r#"m_bucket{le="111"} 4"#));
+        assert_eq!(&lines[last..], [
+            r#"m_bucket{le="4095"} 4"#,
+            r#"m_bucket{le="5119"} 5"#,
+            r#"m_bucket{le="+Inf"} 5"#,
+            "m_sum 5105",
+            "m_count 5",
+        ]);
+
+        // The lines of buckets up to the highest non-empty one do not depend on the values.
+        let mut other: Histogram = Histogram::new();
+        other.record(5000);
+        let other_out = PrometheusExporter::new("m").render(&other);
+        let le = |out: &str| -> Vec<String> {
+            out.lines().map(|l| l.split(' ').next().unwrap().to_string()).collect()
+        };
+        assert_eq!(le(&out), le(&other_out));
+    }
+
+    #[test]
+    fn test_render_sparse() {
+        let out = PrometheusExporter::new("latency_us")
+            .help("Request latency")
+            .label("node", "1")
+            .sparse()
+            .render(&sample_histogram());
+
+        let expected = [
+            "# HELP latency_us Request latency",
+            "# TYPE latency_us histogram",
+            r#"latency_us_bucket{node="1",le="1"} 1"#,
+            r#"latency_us_bucket{node="1",le="2"} 3"#,
+            r#"latency_us_bucket{node="1",le="111"} 4"#,
+            r#"latency_us_bucket{node="1",le="5119"} 5"#,
+            r#"latency_us_bucket{node="1",le="+Inf"} 5"#,
+            r#"latency_us_sum{node="1"} 5105"#,
+            r#"latency_us_count{node="1"} 5"#,
+            "",
+        ]
+        .join("\n");
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_exemplars() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let mut hist = sample_histogram();
+        hist.record_with_exemplar(Exemplar::new(2, "t1").with_timestamp(at));
+        hist.record_with_exemplar(Exemplar::new(6000, "t2").with_timestamp(at));
+
+        let out = PrometheusExporter::new("m").exemplars("trace_id").render(&hist);
+        let lines: Vec<&str> =
+            out.lines().filter(|l| l.contains('#') && !l.starts_with('#')).collect();
+        assert_eq!(lines, vec![
+            r#"m_bucket{le="2"} 4 # {trace_id="t1"} 2 1700000000.123"#,
+            r#"m_bucket{le="6143"} 7 # {trace_id="t2"} 6000 1700000000.123"#,
+        ]);
+
+        // With boundaries, values above the last one go to the +Inf line.
+        let out = PrometheusExporter::new("m").boundaries([10]).exemplars("trace_id").render(&hist);
+        let lines: Vec<&str> =
+            out.lines().filter(|l| l.contains('#') && !l.starts_with('#')).collect();
+        assert_eq!(lines, vec![
+            r#"m_bucket{le="10"} 4 # {trace_id="t1"} 2 1700000000.123"#,
+            r#"m_bucket{le="+Inf"} 7 # {trace_id="t2"} 6000 1700000000.123"#,
+        ]);
+
+        // Exemplars are only written when enabled.
+        assert!(!PrometheusExporter::new("m").render(&hist).contains("trace_id"));
+    }
+
+    #[test]
+    fn test_render_empty_without_labels() {
+        let hist: Histogram = Histogram::new();
+        let out = PrometheusExporter::new("m").render(&hist);
+
+        assert_eq!(
+            out,
+            "# TYPE m histogram\nm_bucket{le=\"+Inf\"} 0\nm_sum 0\nm_count 0\n"
+        );
+    }
+
+    #[test]
+    fn test_render_boundaries() {
+        let out =
+            PrometheusExporter::new("m").boundaries([1000, 10, 1, 10]).render(&sample_histogram());
+
+        let expected = [
+            "# TYPE m histogram",
+            r#"m_bucket{le="1"} 1"#,
+            r#"m_bucket{le="10"} 3"#,
+            r#"m_bucket{le="1000"} 4"#,
+            r#"m_bucket{le="+Inf"} 5"#,
+            "m_sum 5105",
+            "m_count 5",
+            "",
+        ]
+        .join("\n");
+
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_boundary_inside_bucket() {
+        // 100 falls into bucket [96, 111]: it is counted under the next boundary.
+        let mut hist: Histogram = Histogram::new();
+        hist.record(100);
+
+        let out = PrometheusExporter::new("m").boundaries([100, 111]).render(&hist);
+        assert!(out.contains("m_bucket{le=\"100\"} 0\n"));
+        assert!(out.contains("m_bucket{le=\"111\"} 1\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        let out = PrometheusExporter::new("m")
+            .help("a\\b\nc")
+            .label("path", "x\"y\\z\n")
+            .render(&Histogram::<()>::new());
+
+        assert!(out.starts_with("# HELP m a\\\\b\\nc\n"));
+        assert!(out.contains(r#"m_count{path="x\"y\\z\n"} 0"#));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+
+use super::bucket_scale::BucketScale;
+use super::percentile_mode::PercentileMode;
+
+/// Values at an arbitrary list of quantiles, computed in a single pass over the buckets.
+///
+/// Returned by [`Histogram::quantiles()`](super::Histogram::quantiles). Values are listed in
+/// the order the quantiles were requested. `min` and `max` are the exact extremes, see
+/// [`Summary`](super::Summary).
+///
+/// Values are `u64`, or `i64` and `f64` for [`SignedHistogram`](super::SignedHistogram) and
+/// [`FloatHistogram`](super::FloatHistogram).
+///
+/// ```text
+/// [samples: 1000, P50: 448, P99: 896, P99.99: 896, min: 1, max: 1000]
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantiles<V = u64> {
+    /// Number of samples recorded.
+    pub samples: u64,
+    /// Value at each requested quantile.
+    pub values: Vec<QuantileValue<V>>,
+    /// Smallest recorded value, `None` if there is no sample.
+    pub min: Option<V>,
+    /// Largest recorded value, `None` if there is no sample.
+    pub max: Option<V>,
+}
+
+/// The value at one quantile of a [`Quantiles`] result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuantileValue<V = u64> {
+    /// The requested quantile, in `[0, 1]`.
+    pub quantile: f64,
+    /// The estimated value at that quantile.
+    pub value: V,
+}
+
+impl<V: Copy> Quantiles<V> {
+    /// Returns the value at quantile `q`, if it was requested.
+    pub fn get(&self, q: f64) -> Option<V> {
+        self.values.iter().find(|v| v.quantile == q).map(|v| v.value)
+    }
+
+    /// Returns the quantiles `qs` paired with their `values`, in order.
+    pub(crate) fn from_values(
+        samples: u64,
+        qs: &[f64],
+        values: impl IntoIterator<Item = V>,
+        min: Option<V>,
+        max: Option<V>,
+    ) -> Self {
+        Self {
+            samples,
+            values: qs
+                .iter()
+                .zip(values)
+                .map(|(&quantile, value)| QuantileValue { quantile, value })
+                .collect(),
+            min,
+            max,
+        }
+    }
+}
+
+impl<V: fmt::Display> fmt::Display for Quantiles<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[samples: {}", self.samples)?;
+        for v in &self.values {
+            write!(f, ", {}: {}", percentile_label(v.quantile), v.value)?;
+        }
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            write!(f, ", min: {}, max: {}", min, max)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Returns the label of quantile `q`, e.g. `P99.9` for 0.999.
+pub(crate) fn percentile_label(q: f64) -> String {
+    // Round away floating point noise such as 0.999 * 100 = 99.89999999999999.
+    let percent = (q * 100.0 * 1e6).round() / 1e6;
+    format!("P{}", percent)
+}
+
+/// Returns the values at quantiles `qs` of `buckets`, in the order of `qs`, estimated within
+/// their buckets according to `mode`.
+///
+/// Visits each bucket at most once whatever the number of quantiles. Returns all zeros if the
+/// buckets are empty.
+///
+/// # Panics
+///
+/// Panics if a quantile is not in `[0, 1]`.
+pub(crate) fn bucket_quantiles(
+    scale: &impl BucketScale,
+    buckets: &[u64],
+    qs: &[f64],
+    mode: PercentileMode,
+) -> Vec<u64> {
+    for &q in qs {
+        assert!(
+            (0.0..=1.0).contains(&q),
+            "quantile must be in [0, 1], got {}",
+            q
+        );
+    }
+
+    let mut values = vec![0; qs.len()];
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return values;
+    }
+
+    let targets: Vec<u64> =
+        qs.iter().map(|&q| (total as f64 * q).ceil().clamp(1.0, total as f64) as u64).collect();
+    let mut order: Vec<usize> = (0..qs.len()).collect();
+    order.sort_unstable_by_key(|&i| targets[i]);
+
+    let mut pending = order.into_iter().peekable();
+    let mut cumulative = 0u64;
+    for (bucket_index, &count) in buckets.iter().enumerate() {
+        while let Some(&i) = pending.peek() {
+            if cumulative + count < targets[i] {
+                break;
+            }
+            values[i] = mode.estimate(
+                scale.bucket_min_value(bucket_index),
+                scale.bucket_max_value(bucket_index),
+                targets[i] - cumulative,
+                count,
+            );
+            pending.next();
+        }
+        if pending.peek().is_none() {
+            break;
+        }
+        cumulative += count;
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LOG_SCALE;
+
+    #[test]
+    fn test_display() {
+        let q = Quantiles {
+            samples: 3,
+            values: vec![
+                QuantileValue {
+                    quantile: 0.5,
+                    value: 10,
+                },
+                QuantileValue {
+                    quantile: 0.999,
+                    value: 20,
+                },
+                QuantileValue {
+                    quantile: 0.9999,
+                    value: 30,
+                },
+            ],
+            min: Some(1),
+            max: Some(31),
+        };
+        assert_eq!(
+            q.to_string(),
+            "[samples: 3, P50: 10, P99.9: 20, P99.99: 30, min: 1, max: 31]"
+        );
+        assert_eq!(q.get(0.999), Some(20));
+        assert_eq!(q.get(0.9), None);
+
+        let empty: Quantiles = Quantiles {
+            samples: 0,
+            values: vec![],
+            min: None,
+            max: None,
+        };
+        assert_eq!(empty.to_string(), "[samples: 0]");
+    }
+
+    #[test]
+    fn test_bucket_quantiles_unsorted_and_duplicated() {
+        let mut buckets = vec![0; LOG_SCALE.num_buckets()];
+        for v in 1..=100u64 {
+            buckets[LOG_SCALE.calculate_bucket(v)] += 1;
+        }
+
+        let qs = [0.99, 0.0, 0.5, 0.99, 1.0];
+        let values = bucket_quantiles(&LOG_SCALE, &buckets, &qs, PercentileMode::LowerBound);
+        assert_eq!(values, vec![96, 1, 48, 96, 96]);
+
+        let empty = vec![0; LOG_SCALE.num_buckets()];
+        let values = bucket_quantiles(&LOG_SCALE, &empty, &qs, PercentileMode::UpperBound);
+        assert_eq!(values, vec![0; 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "quantile must be in [0, 1], got 1.5")]
+    fn test_bucket_quantiles_out_of_range() {
+        bucket_quantiles(&LOG_SCALE, &[0; 252], &[1.5], PercentileMode::LowerBound);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let q = Quantiles {
+            samples: 2,
+            values: vec![QuantileValue {
+                quantile: 0.5,
+                value: 7,
+            }],
+            min: Some(7),
+            max: Some(9),
+        };
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert_eq!(
+            json,
+            r#"{"samples":2,"values":[{"quantile":0.5,"value":7}],"min":7,"max":9}"#
+        );
+        assert_eq!(serde_json::from_str::<Quantiles>(&json).unwrap(), q);
+    }
+}
@@ -0,0 +1,416 @@
+use std::fmt;
+use std::fmt::Write;
+
+use super::bucket_scale::BucketScale;
+use super::exemplar::Exemplar;
+use super::histogram::Histogram;
+use super::percentile_mode::PercentileMode;
+use super::quantiles::bucket_quantiles;
+use super::quantiles::percentile_label;
+
+/// Unicode blocks of 1/8 to 7/8 of a character, for the fractional end of a bar.
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// The unit of the values recorded in a histogram, used to label them in a readable way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Unit {
+    /// Plain numbers, written as is.
+    #[default]
+    Count,
+    /// Durations in nanoseconds, written as `ns`, `us`, `ms` or `s`.
+    Nanoseconds,
+    /// Durations in microseconds, written as `us`, `ms` or `s`.
+    Microseconds,
+    /// Durations in milliseconds, written as `ms` or `s`.
+    Milliseconds,
+    /// Durations in seconds.
+    Seconds,
+    /// Sizes in bytes, written with binary prefixes: `B`, `KiB`, `MiB`, ...
+    Bytes,
+}
+
+impl Unit {
+    /// Formats `value` with the largest suffix that keeps it at least 1, e.g. `1.5ms` for
+    /// 1500 microseconds.
+    pub fn format(self, value: u64) -> String {
+        const DURATIONS: [(&str, f64); 4] = [("ns", 1.0), ("us", 1e3), ("ms", 1e6), ("s", 1e9)];
+        const BYTES: [(&str, f64); 7] = [
+            ("B", 1.0),
+            ("KiB", 1024.0),
+            ("MiB", 1048576.0),
+            ("GiB", 1073741824.0),
+            ("TiB", 1099511627776.0),
+            ("PiB", 1125899906842624.0),
+            ("EiB", 1152921504606846976.0),
+        ];
+
+        let (suffixes, base): (&[(&str, f64)], f64) = match self {
+            Unit::Count => return value.to_string(),
+            Unit::Nanoseconds => (&DURATIONS, 1.0),
+            Unit::Microseconds => (&DURATIONS[1..], 1e3),
+            Unit::Milliseconds => (&DURATIONS[2..], 1e6),
+            Unit::Seconds => (&DURATIONS[3..], 1e9),
+            Unit::Bytes => (&BYTES, 1.0),
+        };
+
+        let scaled = value as f64 * base;
+        let (suffix, factor) =
+            suffixes.iter().rev().find(|(_, factor)| scaled >= *factor).unwrap_or(&suffixes[0]);
+        format!("{}{}", format_significant(scaled / factor), suffix)
+    }
+}
+
+/// Formats `x` with 3 significant digits, without trailing zeros: `1.5`, `12.3`, `512`.
+fn format_significant(x: f64) -> String {
+    let s = if x >= 100.0 {
+        format!("{:.0}", x)
+    } else if x >= 10.0 {
+        format!("{:.1}", x)
+    } else {
+        format!("{:.2}", x)
+    };
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        s
+    }
+}
+
+/// Draws a [`Histogram`] as a text bar chart, one row per value range.
+///
+/// ```text
+/// samples: 10, min: 1us, max: 1.2ms
+///   1us ..    2us |██████████| 3
+///   3us ..    4us |██████████| 3  P50
+///   5us ..    6us |          | 0
+///   ...
+/// 112us ..  159us |███▎      | 1  P90
+///   ...
+/// 896us .. 1.28ms |███▎      | 1  P99
+/// ```
+///
+/// Rows cover the non-empty range of log buckets, including empty buckets in between so that
+/// gaps are visible. When there are more buckets than [`rows()`](Self::rows), adjacent buckets
+/// are merged evenly. Each row is labeled with the inclusive range of values it covers, in the
+/// [`Unit`] set with [`unit()`](Self::unit), and the rows containing the requested percentiles
+/// are marked. A row ends with the id and value of its latest [`Exemplar`], if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRenderer {
+    rows: usize,
+    width: usize,
+    unit: Unit,
+    percentiles: Vec<f64>,
+    ascii: bool,
+}
+
+impl Default for TextRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextRenderer {
+    /// Creates a renderer with at most 20 rows of 40-character Unicode bars, plain numbers and
+    /// P50, P90 and P99 markers.
+    pub fn new() -> Self {
+        Self {
+            rows: 20,
+            width: 40,
+            unit: Unit::Count,
+            percentiles: vec![0.5, 0.9, 0.99],
+            ascii: false,
+        }
+    }
+
+    /// Sets the maximum number of rows, merging adjacent buckets to fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` is 0.
+    pub fn rows(mut self, rows: usize) -> Self {
+        assert!(rows > 0, "rows must be at least 1");
+        self.rows = rows;
+        self
+    }
+
+    /// Sets the length in characters of the longest bar.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    pub fn width(mut self, width: usize) -> Self {
+        assert!(width > 0, "width must be at least 1");
+        self.width = width;
+        self
+    }
+
+    /// Sets the unit used to label values.
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets the quantiles, in `[0, 1]`, whose rows are marked.
+    pub fn percentiles(mut self, percentiles: impl IntoIterator<Item = f64>) -> Self {
+        self.percentiles = percentiles.into_iter().collect();
+        self
+    }
+
+    /// Draws bars with `#` instead of Unicode blocks, for terminals without Unicode support.
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Renders the histogram aggregate as a bar chart.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a percentile is not in `[0, 1]`.
+    pub fn render<T, const WIDTH: usize>(&self, hist: &Histogram<T, WIDTH>) -> String {
+        let mut out = String::new();
+        self.render_to(hist, &mut out).unwrap();
+        out
+    }
+
+    /// Renders the histogram aggregate into `out`. See [`render()`](Self::render).
+    pub fn render_to<T, const WIDTH: usize>(
+        &self,
+        hist: &Histogram<T, WIDTH>,
+        out: &mut impl Write,
+    ) -> fmt::Result {
+        let log_scale = hist.scale();
+        let buckets = hist.aggregate_buckets();
+        let summary = hist.summary();
+
+        let first = buckets.iter().position(|&c| c > 0);
+        let last = buckets.iter().rposition(|&c| c > 0);
+        let (Some(first), Some(last), Some(min), Some(max)) =
+            (first, last, summary.min(), summary.max())
+        else {
+            return writeln!(out, "samples: 0");
+        };
+
+        writeln!(
+            out,
+            "samples: {}, min: {}, max: {}",
+            summary.count(),
+            self.unit.format(min),
+            self.unit.format(max)
+        )?;
+
+        // Merge `per_row` adjacent buckets into each row.
+        let per_row = (last - first + 1).div_ceil(self.rows);
+        let mut rows: Vec<Row> = (first..=last)
+            .step_by(per_row)
+            .map(|start| {
+                let end = (start + per_row - 1).min(last);
+                Row {
+                    low: self.unit.format(log_scale.bucket_min_value(start)),
+                    high: self.unit.format(log_scale.bucket_max_value(end)),
+                    count: buckets[start..=end].iter().sum(),
+                    markers: vec![],
+                    exemplar: None,
+                }
+            })
+            .collect();
+
+        let values = bucket_quantiles(
+            log_scale,
+            buckets,
+            &self.percentiles,
+            PercentileMode::LowerBound,
+        );
+        for (&q, value) in self.percentiles.iter().zip(values) {
+            let row = (log_scale.calculate_bucket(value) - first) / per_row;
+            rows[row].markers.push(percentile_label(q));
+        }
+
+        for (index, exemplar) in hist.bucket_exemplars() {
+            if !(first..=last).contains(&index) {
+                continue;
+            }
+            let row = &mut rows[(index - first) / per_row];
+            if row.exemplar.is_none_or(|e| exemplar.timestamp >= e.timestamp) {
+                row.exemplar = Some(exemplar);
+            }
+        }
+
+        let low_width = rows.iter().map(|r| r.low.len()).max().unwrap_or(0);
+        let high_width = rows.iter().map(|r| r.high.len()).max().unwrap_or(0);
+        let max_count = rows.iter().map(|r| r.count).max().unwrap_or(0);
+        let count_width = max_count.to_string().len();
+
+        for row in &rows {
+            write!(
+                out,
+                "{:>lw$} .. {:>hw$} |{:<bw$}| {:<cw$}",
+                row.low,
+                row.high,
+                self.bar(row.count, max_count),
+                row.count,
+                lw = low_width,
+                hw = high_width,
+                bw = self.width,
+                cw = count_width,
+            )?;
+            if !row.markers.is_empty() {
+                write!(out, "  {}", row.markers.join(" "))?;
+            }
+            if let Some(exemplar) = row.exemplar {
+                write!(
+                    out,
+                    "  [{}: {}]",
+                    exemplar.id,
+                    self.unit.format(exemplar.value)
+                )?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the bar of a row holding `count` values, `max_count` being the largest row.
+    ///
+    /// A non-empty row always gets a visible bar.
+    fn bar(&self, count: u64, max_count: u64) -> String {
+        if count == 0 {
+            return String::new();
+        }
+
+        // Length in eighths of a character.
+        let eighths = (count as u128 * self.width as u128 * 8 / max_count as u128) as usize;
+        if self.ascii {
+            return "#".repeat((eighths / 8).max(1));
+        }
+
+        let eighths = eighths.max(1);
+        let mut bar = "█".repeat(eighths / 8);
+        if let Some(partial) = (eighths % 8).checked_sub(1) {
+            bar.push(PARTIAL_BLOCKS[partial]);
+        }
+        bar
+    }
+}
+
+/// One line of the chart.
+struct Row<'a> {
+    low: String,
+    high: String,
+    count: u64,
+    markers: Vec<String>,
+    /// The latest exemplar of the buckets of the row.
+    exemplar: Option<&'a Exemplar>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    fn sample_histogram() -> Histogram {
+        let mut hist: Histogram = Histogram::new();
+        for v in [1, 2, 2, 3, 3, 3, 10, 100, 120, 1200] {
+            hist.record(v);
+        }
+        hist
+    }
+
+    #[test]
+    fn test_unit_format() {
+        assert_eq!(Unit::Count.format(123456), "123456");
+        assert_eq!(Unit::Nanoseconds.format(0), "0ns");
+        assert_eq!(Unit::Nanoseconds.format(999), "999ns");
+        assert_eq!(Unit::Nanoseconds.format(1500), "1.5us");
+        assert_eq!(Unit::Microseconds.format(1_234_567), "1.23s");
+        assert_eq!(Unit::Milliseconds.format(25), "25ms");
+        assert_eq!(Unit::Milliseconds.format(60_000), "60s");
+        assert_eq!(Unit::Seconds.format(7), "7s");
+        assert_eq!(Unit::Bytes.format(1023), "1023B");
+        assert_eq!(Unit::Bytes.format(1536), "1.5KiB");
+        assert_eq!(Unit::Bytes.format(10 << 20), "10MiB");
+        assert_eq!(Unit::Bytes.format(u64::MAX), "16EiB");
+    }
+
+    #[test]
+    fn test_render() {
+        let out = TextRenderer::new()
+            .rows(4)
+            .width(8)
+            .unit(Unit::Microseconds)
+            .percentiles([0.0, 0.5, 0.99])
+            .render(&sample_histogram());
+
+        // The log buckets from 1 to 1279 are merged 9 per row to fit in 4 rows.
+        let expected = [
+            "samples: 10, min: 1us, max: 1.2ms",
+            "  1us ..   11us |████████| 7  P0 P50",
+            " 12us ..   55us |        | 0",
+            " 56us ..  255us |██▎     | 2",
+            "256us .. 1.28ms |█▏      | 1  P99",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_one_row_per_bucket() {
+        let mut hist: Histogram = Histogram::new();
+        hist.record_n(4, 3);
+        hist.record_n(6, 1);
+
+        let out = TextRenderer::new().width(6).ascii(true).percentiles([]).render(&hist);
+        let expected = [
+            "samples: 4, min: 4, max: 6",
+            "4 .. 4 |######| 3",
+            "5 .. 5 |      | 0",
+            "6 .. 6 |##    | 1",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_exemplars() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut hist: Histogram = Histogram::new();
+        hist.record_with_exemplar(Exemplar::new(4, "old").with_timestamp(at(1)));
+        hist.record_with_exemplar(Exemplar::new(5, "new").with_timestamp(at(2)));
+        hist.record(6);
+
+        let out = TextRenderer::new().rows(2).width(4).ascii(true).percentiles([]).render(&hist);
+        let expected = [
+            "samples: 3, min: 4, max: 6",
+            "4 .. 5 |####| 2  [new: 5]",
+            "6 .. 6 |##  | 1",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_render_empty() {
+        let hist: Histogram = Histogram::new();
+        assert_eq!(TextRenderer::new().render(&hist), "samples: 0\n");
+    }
+
+    #[test]
+    fn test_bar_min_length() {
+        let renderer = TextRenderer::new().width(4);
+        assert_eq!(renderer.bar(0, 1000), "");
+        assert_eq!(renderer.bar(1, 1000), "▏");
+        assert_eq!(renderer.bar(1000, 1000), "████");
+        assert_eq!(renderer.ascii(true).bar(1, 1000), "#");
+    }
+
+    #[test]
+    #[should_panic(expected = "rows must be at least 1")]
+    fn test_zero_rows_panics() {
+        TextRenderer::new().rows(0);
+    }
+}
@@ -0,0 +1,239 @@
+//! Serde support for [`Histogram`], enabled by the `serde` feature.
+//!
+//! A histogram is serialized as its log scale width, slot capacity and active slots.
+//! Each slot stores its non-empty buckets as `[index, count]` pairs, its exact [`Summary`]
+//! (`null` for an empty slot) and its metadata:
+//!
+//! ```json
+//! {"width":3,"capacity":2,"slots":[{"buckets":[[1,2],[17,1]],
+//!   "summary":{"min":1,"max":42,"sum":44,"sum_of_squares":1766},"data":null}]}
+//! ```
+//!
+//! Exemplars are not serialized. Serializing a histogram with a custom
+//! [`BucketScale`] fails: it would be deserialized with the log scale.
+
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use serde::de::Error;
+use serde::ser;
+
+use super::bucket_scale::BucketScale;
+use super::bucket_scale::HistogramScale;
+use super::codec::MAX_DECODE_CAPACITY;
+use super::histogram::Histogram;
+use super::log_scale::LogScale;
+use super::slot::Slot;
+use super::slot_buckets::SlotBuckets;
+use super::summary::Summary;
+
+#[derive(Serialize)]
+struct HistogramRef<'a, T> {
+    width: usize,
+    capacity: usize,
+    slots: Vec<SlotRef<'a, T>>,
+}
+
+#[derive(Serialize)]
+struct SlotRef<'a, T> {
+    buckets: Vec<(usize, u64)>,
+    summary: Option<SummaryRepr>,
+    data: Option<&'a T>,
+}
+
+#[derive(Deserialize)]
+struct HistogramRepr<T> {
+    width: usize,
+    capacity: usize,
+    slots: Vec<SlotRepr<T>>,
+}
+
+#[derive(Deserialize)]
+struct SlotRepr<T> {
+    buckets: Vec<(usize, u64)>,
+    summary: Option<SummaryRepr>,
+    data: Option<T>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SummaryRepr {
+    min: u64,
+    max: u64,
+    sum: u128,
+    sum_of_squares: u128,
+}
+
+impl SummaryRepr {
+    fn new(summary: &Summary) -> Option<Self> {
+        Some(Self {
+            min: summary.min()?,
+            max: summary.max()?,
+            sum: summary.sum(),
+            sum_of_squares: summary.sum_of_squares(),
+        })
+    }
+}
+
+impl<T, const WIDTH: usize> Serialize for Histogram<T, WIDTH>
+where T: Serialize
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        if !self.scale().is_log() {
+            return Err(ser::Error::custom(
+                "histograms with a custom scale cannot be serialized",
+            ));
+        }
+
+        let slots = self
+            .iter_slots()
+            .map(|slot| SlotRef {
+                buckets: slot.buckets.iter().collect(),
+                summary: SummaryRepr::new(&slot.summary),
+                data: slot.data.as_ref(),
+            })
+            .collect();
+
+        HistogramRef {
+            width: WIDTH,
+            capacity: self.capacity(),
+            slots,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, const WIDTH: usize> Deserialize<'de> for Histogram<T, WIDTH>
+where T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let repr = HistogramRepr::<T>::deserialize(deserializer)?;
+
+        if repr.width != WIDTH {
+            return Err(D::Error::custom(format!(
+                "log scale width mismatch: expected {}, got {}",
+                WIDTH, repr.width
+            )));
+        }
+        if repr.capacity == 0 || repr.capacity > MAX_DECODE_CAPACITY {
+            return Err(D::Error::custom(format!(
+                "invalid slot capacity {}",
+                repr.capacity
+            )));
+        }
+        if repr.slots.is_empty() || repr.slots.len() > repr.capacity {
+            return Err(D::Error::custom(format!(
+                "invalid slot count {}",
+                repr.slots.len()
+            )));
+        }
+
+        let scale = HistogramScale::Log(LogScale::<WIDTH>::new());
+        let num_buckets = scale.num_buckets();
+        let mut slots = Vec::with_capacity(repr.slots.len());
+        for s in repr.slots {
+            let mut buckets = vec![0; num_buckets];
+            for (index, count) in s.buckets {
+                let bucket = buckets.get_mut(index).ok_or_else(|| {
+                    D::Error::custom(format!("bucket index {} out of range", index))
+                })?;
+                *bucket = count;
+            }
+            let summary = match s.summary {
+                None if buckets.iter().all(|&c| c == 0) => Summary::EMPTY,
+                None => return Err(D::Error::custom("non-empty slot without summary")),
+                Some(r) => {
+                    Summary::from_stored(&scale, &buckets, r.min, r.max, r.sum, r.sum_of_squares)
+                        .ok_or_else(|| D::Error::custom("slot min or max outside its buckets"))?
+                }
+            };
+            slots.push(Slot::with_buckets(
+                SlotBuckets::from_dense(buckets),
+                summary,
+                s.data,
+            ));
+        }
+
+        Histogram::from_slots(scale, repr.capacity, slots)
+            .ok_or_else(|| D::Error::custom("aggregate bucket count overflows u64"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::histogram::Histogram;
+    use crate::histogram::LinearScale;
+
+    #[test]
+    fn test_serde_format() {
+        let mut hist: Histogram<String> = Histogram::with_slots(2);
+        hist.record(1);
+        hist.record(1);
+        hist.record(42);
+
+        let json = serde_json::to_string(&hist).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"width":3,"capacity":2,"slots":[{"buckets":[[1,2],[17,1]],"#,
+                r#""summary":{"min":1,"max":42,"sum":44,"sum_of_squares":1766},"data":null}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_serde_without_summary() {
+        let json = r#"{"width":3,"capacity":2,"slots":[{"buckets":[[1,2],[17,1]],"data":null}]}"#;
+        let err = serde_json::from_str::<Histogram>(json).unwrap_err().to_string();
+        assert!(err.contains("non-empty slot without summary"));
+
+        let json = r#"{"width":3,"capacity":2,"slots":[{"buckets":[],"data":null}]}"#;
+        let hist: Histogram = serde_json::from_str(json).unwrap();
+        assert_eq!(hist.summary(), Histogram::<()>::new().summary());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut hist: Histogram<String> = Histogram::with_slots(3);
+        for v in [0, 7, 100, 100, u64::MAX] {
+            hist.record(v);
+        }
+        hist.advance("minute-1".to_string());
+        hist.record(12345);
+
+        let json = serde_json::to_string(&hist).unwrap();
+        let decoded: Histogram<String> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, hist);
+    }
+
+    #[test]
+    fn test_serde_custom_scale_fails() {
+        let hist: Histogram = Histogram::with_scale(LinearScale::new(1, 10), 1);
+        let err = serde_json::to_string(&hist).unwrap_err().to_string();
+        assert!(err.contains("custom scale"));
+    }
+
+    #[test]
+    fn test_serde_rejects_invalid() {
+        let de = |s: &str| serde_json::from_str::<Histogram>(s).unwrap_err().to_string();
+
+        assert!(
+            de(r#"{"width":4,"capacity":1,"slots":[{"buckets":[],"data":null}]}"#)
+                .contains("width mismatch")
+        );
+        assert!(de(r#"{"width":3,"capacity":0,"slots":[]}"#).contains("invalid slot capacity"));
+        assert!(de(r#"{"width":3,"capacity":1,"slots":[]}"#).contains("invalid slot count"));
+        assert!(
+            de(r#"{"width":3,"capacity":1,"slots":[{"buckets":[[252,1]],"data":null}]}"#)
+                .contains("out of range")
+        );
+        assert!(
+            de(r#"{"width":3,"capacity":1,"slots":[{"buckets":[[1,1]],
+                "summary":{"min":1,"max":2,"sum":1,"sum_of_squares":1},"data":null}]}"#)
+            .contains("outside its buckets")
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use super::bucket_scale::BucketScale;
+use super::histogram::Histogram;
+use super::histogram::bucket_value_at_rank;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+use super::quantiles::Quantiles;
+
+/// A histogram of `i64` values, e.g. clock skew, which can be negative.
+///
+/// Values are split by sign onto two log-scale [`Histogram`]s sharing the same slots:
+/// [`negative()`](Self::negative) holds the magnitudes of the negative values, and
+/// [`positive()`](Self::positive) holds zero and the positive values. Zero has its own bucket,
+/// bucket 0 of the positive side. The relative precision of a value is therefore the same as
+/// for its magnitude in a [`Histogram`].
+///
+/// Percentiles are taken over all values in signed order, from the most negative to the most
+/// positive. [`PercentileMode`] biases keep their meaning: `LowerBound` never reports a value
+/// above the exact one, on either side of zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedHistogram<const WIDTH: usize = 3> {
+    negative: Histogram<(), WIDTH>,
+    positive: Histogram<(), WIDTH>,
+}
+
+impl<const WIDTH: usize> Default for SignedHistogram<WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WIDTH: usize> SignedHistogram<WIDTH> {
+    /// Creates a new signed histogram with 1 slot.
+    pub fn new() -> Self {
+        Self::with_slots(1)
+    }
+
+    /// Creates a new signed histogram with the specified slot capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_slots(capacity: usize) -> Self {
+        Self {
+            negative: Histogram::with_slots(capacity),
+            positive: Histogram::with_slots(capacity),
+        }
+    }
+
+    /// Creates a new signed histogram whose magnitudes are bucketed by `scale` instead of the
+    /// log scale of `WIDTH`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub(crate) fn with_scale(scale: impl BucketScale + Clone, capacity: usize) -> Self {
+        Self {
+            negative: Histogram::with_scale(scale.clone(), capacity),
+            positive: Histogram::with_scale(scale, capacity),
+        }
+    }
+}
+
+impl<const WIDTH: usize> SignedHistogram<WIDTH> {
+    /// Records a value to the current (last) slot.
+    pub fn record(&mut self, value: i64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `count` occurrences of `value` to the current (last) slot.
+    pub fn record_n(&mut self, value: i64, count: u64) {
+        if value < 0 {
+            self.negative.record_n(value.unsigned_abs(), count);
+        } else {
+            self.positive.record_n(value as u64, count);
+        }
+    }
+
+    /// Advances to a new slot, evicting the oldest if at capacity.
+    pub fn advance(&mut self) {
+        self.negative.advance(());
+        self.positive.advance(());
+    }
+
+    /// Adds all values of `other` to the current slot.
+    pub fn merge(&mut self, other: &SignedHistogram<WIDTH>) {
+        // Both sides always use the log scale of `WIDTH`, or for the inner histogram of a
+        // `FloatHistogram`, its float scale.
+        self.negative.merge(&other.negative).unwrap();
+        self.positive.merge(&other.positive).unwrap();
+    }
+
+    /// Returns the histogram of the magnitudes of the negative values.
+    pub fn negative(&self) -> &Histogram<(), WIDTH> {
+        &self.negative
+    }
+
+    /// Returns the histogram of zero and the positive values.
+    pub fn positive(&self) -> &Histogram<(), WIDTH> {
+        &self.positive
+    }
+
+    /// Returns the total number of values recorded across all slots.
+    pub fn total(&self) -> u64 {
+        self.negative.total() + self.positive.total()
+    }
+
+    /// Returns the smallest recorded value, or `None` if there is no value.
+    pub fn min(&self) -> Option<i64> {
+        match self.negative.summary().max() {
+            Some(magnitude) => Some(negate(magnitude)),
+            None => self.positive.summary().min().map(|v| v as i64),
+        }
+    }
+
+    /// Returns the largest recorded value, or `None` if there is no value.
+    pub fn max(&self) -> Option<i64> {
+        match self.positive.summary().max() {
+            Some(v) => Some(v as i64),
+            None => self.negative.summary().min().map(negate),
+        }
+    }
+
+    /// Returns the exact arithmetic mean, or `None` if there is no value.
+    pub fn mean(&self) -> Option<f64> {
+        let total = self.total();
+        let sum = self.positive.summary().sum() as f64 - self.negative.summary().sum() as f64;
+        (total > 0).then(|| sum / total as f64)
+    }
+
+    /// Calculates the value at the given percentile.
+    ///
+    /// Like [`Histogram::percentile()`], the value is the lower bound of its bucket: for a
+    /// negative value, the bucket of the largest magnitude. Returns `0` if the histogram is
+    /// empty.
+    pub fn percentile(&self, p: f64) -> i64 {
+        self.percentile_with_mode(p, PercentileMode::LowerBound)
+    }
+
+    /// Calculates the value at the given percentile, estimated within its bucket according to
+    /// `mode`. Returns `0` if the histogram is empty.
+    pub fn percentile_with_mode(&self, p: f64, mode: PercentileMode) -> i64 {
+        let total = self.total();
+        if total == 0 {
+            return 0;
+        }
+        let target = (total as f64 * p).ceil().clamp(1.0, total as f64) as u64;
+        self.value_at_rank(target, mode)
+    }
+
+    /// Returns common percentile statistics: samples, P0.1, P1, P5, P10, P50, P90, P99, P99.9.
+    pub fn percentile_stats(&self) -> PercentileStats<i64> {
+        self.percentile_stats_with_mode(PercentileMode::LowerBound)
+    }
+
+    /// Returns common percentile statistics, estimated within their buckets according to
+    /// `mode`.
+    pub fn percentile_stats_with_mode(&self, mode: PercentileMode) -> PercentileStats<i64> {
+        let values = self.values_at(&PercentileStats::QUANTILES, mode);
+        PercentileStats::from_values(self.total(), &values)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, in the order of `qs`.
+    ///
+    /// Values are estimated like [`percentile()`](Self::percentile). The result also holds
+    /// the exact min and max.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a quantile is not in `[0, 1]`.
+    pub fn quantiles(&self, qs: &[f64]) -> Quantiles<i64> {
+        self.quantiles_with_mode(qs, PercentileMode::LowerBound)
+    }
+
+    /// Returns the values at each of the quantiles `qs`, estimated within their buckets
+    /// according to `mode`. See [`quantiles()`](Self::quantiles).
+    pub fn quantiles_with_mode(&self, qs: &[f64], mode: PercentileMode) -> Quantiles<i64> {
+        let values = self.values_at(qs, mode);
+        Quantiles::from_values(self.total(), qs, values, self.min(), self.max())
+    }
+
+    /// Returns the values at each of the quantiles `qs`, in the order of `qs`.
+    pub(crate) fn values_at(&self, qs: &[f64], mode: PercentileMode) -> Vec<i64> {
+        qs.iter()
+            .map(|&q| {
+                assert!(
+                    (0.0..=1.0).contains(&q),
+                    "quantile must be in [0, 1], got {}",
+                    q
+                );
+                self.percentile_with_mode(q, mode)
+            })
+            .collect()
+    }
+
+    /// Estimates the value of rank `rank` (1-based) in signed order.
+    fn value_at_rank(&self, rank: u64, mode: PercentileMode) -> i64 {
+        let negative_total = self.negative.total();
+        if rank <= negative_total {
+            // Ascending signed order is descending magnitude order.
+            let magnitude = bucket_value_at_rank(
+                self.negative.scale(),
+                self.negative.aggregate_buckets(),
+                negative_total - rank + 1,
+                mode.mirrored(),
+            );
+            negate(magnitude)
+        } else {
+            let value = bucket_value_at_rank(
+                self.positive.scale(),
+                self.positive.aggregate_buckets(),
+                rank - negative_total,
+                mode,
+            );
+            value.min(i64::MAX as u64) as i64
+        }
+    }
+}
+
+/// Returns `-magnitude`, saturating at `i64::MIN`.
+///
+/// A bucket estimate may exceed `2^63`, the magnitude of `i64::MIN`.
+fn negate(magnitude: u64) -> i64 {
+    0i64.saturating_sub_unsigned(magnitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_percentile() {
+        let mut hist: SignedHistogram = SignedHistogram::new();
+        assert_eq!(hist.percentile(0.5), 0);
+        assert_eq!((hist.min(), hist.max(), hist.mean()), (None, None, None));
+        assert_eq!(hist.quantiles(&[0.5]).to_string(), "[samples: 0, P50: 0]");
+
+        for v in [-100, -5, 0, 0, 3, 100] {
+            hist.record(v);
+        }
+        assert_eq!(hist.total(), 6);
+        assert_eq!(hist.negative().total(), 2);
+        assert_eq!(hist.positive().total(), 4);
+        assert_eq!(hist.min(), Some(-100));
+        assert_eq!(hist.max(), Some(100));
+        assert_eq!(hist.mean(), Some(-2.0 / 6.0));
+
+        // -100 is in the bucket [-111, -96]: the lower bound is -111.
+        assert_eq!(hist.percentile(0.0), -111);
+        assert_eq!(hist.percentile(1.0 / 6.0), -111);
+        assert_eq!(hist.percentile(2.0 / 6.0), -5);
+        assert_eq!(hist.percentile(0.5), 0);
+        assert_eq!(hist.percentile(5.0 / 6.0), 3);
+        assert_eq!(hist.percentile(1.0), 96);
+
+        let quantiles = hist.quantiles(&[1.0, 0.0]);
+        assert_eq!(
+            quantiles.to_string(),
+            "[samples: 6, P100: 96, P0: -111, min: -100, max: 100]"
+        );
+        assert_eq!(
+            (quantiles.get(1.0), quantiles.get(0.0)),
+            (Some(96), Some(-111))
+        );
+        let stats = hist.percentile_stats();
+        assert_eq!(
+            (stats.samples, stats.p0_1, stats.p50, stats.p99_9),
+            (6, -111, 0, 96)
+        );
+        let quantiles = hist.quantiles_with_mode(&[0.0, 1.0], PercentileMode::UpperBound);
+        let values: Vec<i64> = quantiles.values.iter().map(|v| v.value).collect();
+        assert_eq!(values, vec![-96, 111]);
+    }
+
+    #[test]
+    fn test_lower_bound_never_above_exact() {
+        let mut values: Vec<i64> = (-1000..1000).map(|v| v * 7919 % 100_003).collect();
+        let mut hist: SignedHistogram = SignedHistogram::new();
+        for &v in &values {
+            hist.record(v);
+        }
+        values.sort_unstable();
+
+        for (i, &exact) in values.iter().enumerate() {
+            let p = (i + 1) as f64 / values.len() as f64;
+            assert!(hist.percentile_with_mode(p, PercentileMode::LowerBound) <= exact);
+            assert!(hist.percentile_with_mode(p, PercentileMode::UpperBound) >= exact);
+        }
+    }
+
+    #[test]
+    fn test_extremes() {
+        let mut hist: SignedHistogram = SignedHistogram::new();
+        hist.record(i64::MIN);
+        hist.record(i64::MAX);
+
+        assert_eq!(hist.min(), Some(i64::MIN));
+        assert_eq!(hist.max(), Some(i64::MAX));
+        assert_eq!(hist.percentile(0.0), i64::MIN);
+        assert_eq!(hist.percentile(1.0), 7 << 60);
+        assert_eq!(
+            hist.percentile_with_mode(1.0, PercentileMode::UpperBound),
+            i64::MAX
+        );
+    }
+
+    #[test]
+    fn test_slots_and_merge() {
+        let mut hist: SignedHistogram = SignedHistogram::with_slots(2);
+        hist.record(-1);
+        hist.advance();
+        hist.record(1);
+        hist.advance();
+        assert_eq!(hist.total(), 1);
+        assert_eq!(hist.min(), Some(1));
+
+        let mut other: SignedHistogram = SignedHistogram::new();
+        other.record_n(-2, 3);
+        hist.merge(&other);
+        assert_eq!(hist.total(), 4);
+        assert_eq!(hist.percentile(0.75), -2);
+    }
+}
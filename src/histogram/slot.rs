@@ -1,24 +1,67 @@
+use std::mem::size_of;
+
+use super::exemplar::Exemplar;
+use super::slot_buckets::SlotBuckets;
+use super::summary::Summary;
+
 /// A single slot containing bucket counts and user-defined metadata.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct Slot<T> {
     /// Count of samples in each bucket.
-    pub(crate) buckets: Vec<u64>,
+    pub(crate) buckets: SlotBuckets,
+    /// Exact statistics of the samples in this slot.
+    pub(crate) summary: Summary,
     /// User-defined metadata for this slot. Only set when slot is activated via `advance()`.
     pub(crate) data: Option<T>,
+    /// The latest exemplar of each bucket that has one, sorted by bucket index.
+    pub(crate) exemplars: Vec<(usize, Exemplar)>,
 }
 
 impl<T> Slot<T> {
     pub(crate) fn new(num_buckets: usize) -> Self {
+        Self::with_buckets(SlotBuckets::new(num_buckets), Summary::EMPTY, None)
+    }
+
+    /// Creates a slot holding `buckets`, without exemplars.
+    pub(crate) fn with_buckets(buckets: SlotBuckets, summary: Summary, data: Option<T>) -> Self {
         Self {
-            buckets: vec![0; num_buckets],
-            data: None,
+            buckets,
+            summary,
+            data,
+            exemplars: Vec::new(),
         }
     }
 
+    /// Keeps `exemplar` for bucket `index`, unless the bucket has a more recent one.
+    pub(crate) fn offer_exemplar(&mut self, index: usize, exemplar: Exemplar) {
+        match self.exemplars.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(pos) => {
+                if exemplar.timestamp >= self.exemplars[pos].1.timestamp {
+                    self.exemplars[pos].1 = exemplar;
+                }
+            }
+            Err(pos) => self.exemplars.insert(pos, (index, exemplar)),
+        }
+    }
+
+    /// Drops the exemplars of buckets that became empty.
+    pub(crate) fn retain_exemplars(&mut self) {
+        let buckets = &self.buckets;
+        self.exemplars.retain(|(index, _)| buckets.get(*index) > 0);
+    }
+
+    /// Returns the number of heap bytes used by the exemplars.
+    pub(crate) fn exemplars_heap_size(&self) -> usize {
+        self.exemplars.capacity() * size_of::<(usize, Exemplar)>()
+            + self.exemplars.iter().map(|(_, e)| e.id.capacity()).sum::<usize>()
+    }
+
     /// Clears the slot: resets all bucket counts to zero and removes user data.
     #[cfg(test)]
     pub(crate) fn clear(&mut self) {
-        self.buckets.fill(0);
+        self.buckets.clear();
+        self.summary = Summary::EMPTY;
         self.data = None;
+        self.exemplars.clear();
     }
 }
@@ -0,0 +1,288 @@
+use std::borrow::Cow;
+use std::iter::Enumerate;
+use std::iter::Zip;
+use std::mem::size_of;
+use std::slice;
+
+/// A slot switches to dense storage once more than `1 / DENSE_FRACTION` of its buckets are
+/// non-empty.
+///
+/// A sparse entry takes 12 bytes against 8 for a dense bucket, and inserting one shifts the
+/// entries after it; below this fraction, sparse storage uses at most 3/8 of the dense size.
+const DENSE_FRACTION: usize = 4;
+
+/// The bucket counts of a slot, stored sparse while few buckets are non-empty.
+///
+/// Most slots only see values in a handful of buckets, e.g. latencies within one or two orders
+/// of magnitude. Sparse storage keeps the non-empty buckets as sorted `(index, count)` pairs,
+/// and switches to a dense `Vec<u64>` of all buckets once too many are used. It never switches
+/// back, except when rebuilt with [`from_dense()`](Self::from_dense).
+#[derive(Debug, Clone)]
+pub(crate) struct SlotBuckets {
+    num_buckets: usize,
+    repr: Repr,
+}
+
+#[derive(Debug, Clone)]
+enum Repr {
+    /// Indices of the non-empty buckets in ascending order, and their counts.
+    Sparse { indices: Vec<u32>, counts: Vec<u64> },
+    /// Count of every bucket.
+    Dense(Vec<u64>),
+}
+
+impl SlotBuckets {
+    /// Creates `num_buckets` empty buckets, in sparse storage.
+    pub(crate) fn new(num_buckets: usize) -> Self {
+        debug_assert!(num_buckets <= u32::MAX as usize);
+        Self {
+            num_buckets,
+            repr: Repr::Sparse {
+                indices: Vec::new(),
+                counts: Vec::new(),
+            },
+        }
+    }
+
+    /// Creates buckets holding the counts of `buckets`, in dense storage.
+    pub(crate) fn dense(buckets: Vec<u64>) -> Self {
+        Self {
+            num_buckets: buckets.len(),
+            repr: Repr::Dense(buckets),
+        }
+    }
+
+    /// Creates buckets holding the counts of `buckets`, in sparse storage if few are
+    /// non-empty.
+    pub(crate) fn from_dense(buckets: Vec<u64>) -> Self {
+        let non_empty = buckets.iter().filter(|&&c| c > 0).count();
+        if non_empty * DENSE_FRACTION > buckets.len() {
+            return Self::dense(buckets);
+        }
+
+        let mut sparse = Self::new(buckets.len());
+        if let Repr::Sparse { indices, counts } = &mut sparse.repr {
+            for (index, &count) in buckets.iter().enumerate().filter(|(_, c)| **c > 0) {
+                indices.push(index as u32);
+                counts.push(count);
+            }
+        }
+        sparse
+    }
+
+    /// Returns the count of bucket `index`.
+    pub(crate) fn get(&self, index: usize) -> u64 {
+        match &self.repr {
+            Repr::Sparse { indices, counts } => {
+                indices.binary_search(&(index as u32)).map_or(0, |pos| counts[pos])
+            }
+            Repr::Dense(buckets) => buckets[index],
+        }
+    }
+
+    /// Adds `count` to bucket `index`, switching to dense storage if it gets too full.
+    #[inline]
+    pub(crate) fn add(&mut self, index: usize, count: u64) {
+        debug_assert!(index < self.num_buckets);
+
+        match &mut self.repr {
+            Repr::Dense(buckets) => buckets[index] += count,
+            Repr::Sparse { indices, counts } => match indices.binary_search(&(index as u32)) {
+                Ok(pos) => counts[pos] += count,
+                Err(pos) if (indices.len() + 1) * DENSE_FRACTION <= self.num_buckets => {
+                    indices.insert(pos, index as u32);
+                    counts.insert(pos, count);
+                }
+                Err(_) => {
+                    self.densify();
+                    self.add(index, count);
+                }
+            },
+        }
+    }
+
+    /// Subtracts `count` from bucket `index`, which must hold at least `count`.
+    pub(crate) fn sub(&mut self, index: usize, count: u64) {
+        match &mut self.repr {
+            Repr::Sparse { indices, counts } => {
+                let Ok(pos) = indices.binary_search(&(index as u32)) else {
+                    debug_assert_eq!(count, 0);
+                    return;
+                };
+                counts[pos] -= count;
+                if counts[pos] == 0 {
+                    indices.remove(pos);
+                    counts.remove(pos);
+                }
+            }
+            Repr::Dense(buckets) => buckets[index] -= count,
+        }
+    }
+
+    /// Returns the sum of all bucket counts.
+    pub(crate) fn total(&self) -> u64 {
+        match &self.repr {
+            Repr::Sparse { counts, .. } => counts.iter().sum(),
+            Repr::Dense(buckets) => buckets.iter().sum(),
+        }
+    }
+
+    /// Returns the `(index, count)` of the non-empty buckets, in ascending order of index.
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        match &self.repr {
+            Repr::Sparse { indices, counts } => Iter::Sparse(indices.iter().zip(counts.iter())),
+            Repr::Dense(buckets) => Iter::Dense(buckets.iter().enumerate()),
+        }
+    }
+
+    /// Returns the count of every bucket, building it for sparse storage.
+    pub(crate) fn to_dense(&self) -> Cow<'_, [u64]> {
+        match &self.repr {
+            Repr::Sparse { .. } => Cow::Owned(self.build_dense()),
+            Repr::Dense(buckets) => Cow::Borrowed(buckets),
+        }
+    }
+
+    /// Consumes the buckets, returning the count of every bucket.
+    pub(crate) fn into_dense(self) -> Vec<u64> {
+        match self.repr {
+            Repr::Sparse { .. } => self.build_dense(),
+            Repr::Dense(buckets) => buckets,
+        }
+    }
+
+    /// Returns the number of heap bytes used to store the counts.
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.repr {
+            Repr::Sparse { indices, counts } => {
+                indices.capacity() * size_of::<u32>() + counts.capacity() * size_of::<u64>()
+            }
+            Repr::Dense(buckets) => buckets.capacity() * size_of::<u64>(),
+        }
+    }
+
+    /// Resets all counts to zero, in sparse storage.
+    #[cfg(test)]
+    pub(crate) fn clear(&mut self) {
+        *self = Self::new(self.num_buckets);
+    }
+
+    /// Returns `true` if the counts are stored sparse.
+    #[cfg(test)]
+    pub(crate) fn is_sparse(&self) -> bool {
+        matches!(self.repr, Repr::Sparse { .. })
+    }
+
+    fn build_dense(&self) -> Vec<u64> {
+        let mut buckets = vec![0; self.num_buckets];
+        for (index, count) in self.iter() {
+            buckets[index] = count;
+        }
+        buckets
+    }
+
+    /// Switches to dense storage, if not already.
+    pub(crate) fn densify(&mut self) {
+        if let Repr::Sparse { .. } = self.repr {
+            self.repr = Repr::Dense(self.build_dense());
+        }
+    }
+}
+
+impl PartialEq for SlotBuckets {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_buckets == other.num_buckets && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for SlotBuckets {}
+
+/// Iterator over the non-empty buckets of a [`SlotBuckets`].
+pub(crate) enum Iter<'a> {
+    Sparse(Zip<slice::Iter<'a, u32>, slice::Iter<'a, u64>>),
+    Dense(Enumerate<slice::Iter<'a, u64>>),
+}
+
+impl Iterator for Iter<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Sparse(it) => it.next().map(|(&index, &count)| (index as usize, count)),
+            Iter::Dense(it) => it.find(|(_, count)| **count > 0).map(|(i, &c)| (i, c)),
+        }
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Sparse(it) => it.next_back().map(|(&index, &count)| (index as usize, count)),
+            Iter::Dense(it) => it.rfind(|(_, count)| **count > 0).map(|(i, &c)| (i, c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_to_dense() {
+        let mut b = SlotBuckets::new(16);
+        b.add(9, 2);
+        b.add(3, 1);
+        b.add(9, 1);
+        assert!(b.is_sparse());
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![(3, 1), (9, 3)]);
+        assert_eq!(b.iter().next_back(), Some((9, 3)));
+        assert_eq!((b.get(9), b.get(4), b.total()), (3, 0, 4));
+        assert!(b.heap_size() < 16 * 8);
+
+        // More than 16 / 4 non-empty buckets.
+        let sparse = b.clone();
+        b.add(0, 1);
+        b.add(15, 1);
+        assert!(b.is_sparse());
+        b.add(7, 1);
+        assert!(!b.is_sparse());
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![
+            (0, 1),
+            (3, 1),
+            (7, 1),
+            (9, 3),
+            (15, 1)
+        ]);
+        assert_eq!(b.heap_size(), 16 * 8);
+
+        for index in [0, 7, 15] {
+            b.sub(index, 1);
+        }
+        assert_eq!(b, sparse);
+        assert!(!b.is_sparse());
+    }
+
+    #[test]
+    fn test_sub_removes_entry() {
+        let mut b = SlotBuckets::new(16);
+        b.add(5, 2);
+        b.sub(5, 2);
+        b.sub(6, 0);
+        assert_eq!(b, SlotBuckets::new(16));
+        assert_eq!(b.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_from_dense_round_trip() {
+        let mut dense = vec![0; 16];
+        dense[2] = 4;
+        let b = SlotBuckets::from_dense(dense.clone());
+        assert!(b.is_sparse());
+        assert_eq!(b.to_dense().as_ref(), dense.as_slice());
+
+        dense[1..=5].fill(1);
+        let b = SlotBuckets::from_dense(dense.clone());
+        assert!(!b.is_sparse());
+        assert_eq!(b.into_dense(), dense);
+    }
+}
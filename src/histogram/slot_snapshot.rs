@@ -0,0 +1,101 @@
+use super::bucket::Bucket;
+use super::bucket::non_empty_buckets;
+use super::bucket_scale::HistogramScale;
+use super::exemplar::Exemplar;
+use super::histogram::Histogram;
+use super::histogram::bucket_percentile;
+use super::histogram::bucket_percentile_stats;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+use super::slot::Slot;
+use super::summary::Summary;
+
+/// An owned slot removed from a [`Histogram`], with its bucket counts and metadata.
+///
+/// Returned by [`Histogram::advance_and_take()`] when the oldest slot is evicted, so that it
+/// can be archived before it is dropped, e.g. by converting it with
+/// [`into_histogram()`](Self::into_histogram) and encoding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotSnapshot<T, const WIDTH: usize = 3> {
+    scale: HistogramScale<WIDTH>,
+    buckets: Vec<u64>,
+    summary: Summary,
+    data: Option<T>,
+    exemplars: Vec<Exemplar>,
+}
+
+impl<T, const WIDTH: usize> SlotSnapshot<T, WIDTH> {
+    pub(crate) fn new(scale: HistogramScale<WIDTH>, slot: Slot<T>) -> Self {
+        Self {
+            scale,
+            buckets: slot.buckets.into_dense(),
+            summary: slot.summary,
+            data: slot.data,
+            exemplars: slot.exemplars.into_iter().map(|(_, e)| e).collect(),
+        }
+    }
+
+    /// Returns the user-defined metadata passed to [`Histogram::advance()`] when this slot was
+    /// activated.
+    ///
+    /// The initial slot of a histogram has no metadata.
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    /// Consumes the snapshot, returning its metadata.
+    pub fn into_data(self) -> Option<T> {
+        self.data
+    }
+
+    /// Returns the count of each bucket, indexed like the log scale's buckets.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Returns the latest exemplar of each bucket of this slot, in ascending order of value.
+    pub fn exemplars(&self) -> &[Exemplar] {
+        &self.exemplars
+    }
+
+    /// Returns the non-empty buckets of this slot, in ascending order of value.
+    pub fn non_empty_buckets(&self) -> impl DoubleEndedIterator<Item = Bucket> + '_ {
+        non_empty_buckets(&self.scale, &self.buckets)
+    }
+
+    /// Returns the number of values recorded in this slot.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Calculates the value at the given percentile within this slot.
+    ///
+    /// See [`Histogram::percentile()`].
+    pub fn percentile(&self, p: f64) -> u64 {
+        bucket_percentile(
+            &self.scale,
+            &self.buckets,
+            self.total(),
+            p,
+            PercentileMode::LowerBound,
+        )
+    }
+
+    /// Returns common percentile statistics of this slot.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        bucket_percentile_stats(&self.scale, &self.buckets, PercentileMode::LowerBound)
+    }
+
+    /// Returns the exact min, max, sum and sum of squares of this slot.
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    /// Converts the snapshot into a single-slot histogram holding its samples.
+    ///
+    /// The metadata and exemplars are dropped; use [`data()`](Self::data) and
+    /// [`exemplars()`](Self::exemplars) first to keep them.
+    pub fn into_histogram(self) -> Histogram<T, WIDTH> {
+        Histogram::from_buckets(self.scale, self.buckets, self.summary)
+    }
+}
@@ -0,0 +1,84 @@
+use super::bucket::Bucket;
+use super::bucket::sparse_buckets;
+use super::bucket_scale::HistogramScale;
+use super::exemplar::Exemplar;
+use super::histogram::bucket_percentile;
+use super::histogram::bucket_percentile_stats;
+use super::percentile_mode::PercentileMode;
+use super::percentile_stats::PercentileStats;
+use super::slot::Slot;
+use super::summary::Summary;
+
+/// A read-only view of a single [`Histogram`](super::Histogram) slot.
+///
+/// Obtained from [`Histogram::get_slot()`](super::Histogram::get_slot) or
+/// [`Histogram::slots()`](super::Histogram::slots).
+#[derive(Debug)]
+pub struct SlotView<'a, T, const WIDTH: usize = 3> {
+    scale: &'a HistogramScale<WIDTH>,
+    slot: &'a Slot<T>,
+}
+
+impl<T, const WIDTH: usize> Clone for SlotView<'_, T, WIDTH> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, const WIDTH: usize> Copy for SlotView<'_, T, WIDTH> {}
+
+impl<'a, T, const WIDTH: usize> SlotView<'a, T, WIDTH> {
+    pub(crate) fn new(scale: &'a HistogramScale<WIDTH>, slot: &'a Slot<T>) -> Self {
+        Self { scale, slot }
+    }
+
+    /// Returns the user-defined metadata passed to
+    /// [`Histogram::advance()`](super::Histogram::advance) when this slot was activated.
+    ///
+    /// The initial slot of a histogram has no metadata.
+    pub fn data(&self) -> Option<&'a T> {
+        self.slot.data.as_ref()
+    }
+
+    /// Returns the number of values recorded in this slot.
+    pub fn total(&self) -> u64 {
+        self.slot.buckets.total()
+    }
+
+    /// Calculates the value at the given percentile within this slot.
+    ///
+    /// See [`Histogram::percentile()`](super::Histogram::percentile).
+    pub fn percentile(&self, p: f64) -> u64 {
+        bucket_percentile(
+            self.scale,
+            &self.slot.buckets.to_dense(),
+            self.total(),
+            p,
+            PercentileMode::LowerBound,
+        )
+    }
+
+    /// Returns common percentile statistics of this slot.
+    pub fn percentile_stats(&self) -> PercentileStats {
+        bucket_percentile_stats(
+            self.scale,
+            &self.slot.buckets.to_dense(),
+            PercentileMode::LowerBound,
+        )
+    }
+
+    /// Returns the exact min, max, sum and sum of squares of this slot.
+    pub fn summary(&self) -> Summary {
+        self.slot.summary
+    }
+
+    /// Returns the latest exemplar of each bucket of this slot, in ascending order of value.
+    pub fn exemplars(&self) -> impl DoubleEndedIterator<Item = &'a Exemplar> + 'a {
+        self.slot.exemplars.iter().map(|(_, e)| e)
+    }
+
+    /// Returns the non-empty buckets of this slot, in ascending order of value.
+    pub fn non_empty_buckets(&self) -> impl DoubleEndedIterator<Item = Bucket> + 'a {
+        sparse_buckets(self.scale, self.slot.buckets.iter())
+    }
+}
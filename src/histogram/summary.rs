@@ -0,0 +1,367 @@
+use std::fmt;
+
+use super::bucket_scale::BucketScale;
+
+/// Exact summary statistics of the values recorded in a histogram or a slot.
+///
+/// Unlike percentiles, which are limited by the bucket resolution, `min`, `max`, `sum` and
+/// `sum_of_squares` are tracked from the recorded values themselves.
+///
+/// `sum` never overflows, since at most `u64::MAX` values of at most `u64::MAX` are counted.
+/// `sum_of_squares` saturates at `u128::MAX`, which only happens with values close to `2^64`;
+/// [`variance()`](Self::variance) and [`stddev()`](Self::stddev) then return `None`.
+///
+/// After a subtraction, e.g. in [`Histogram::difference()`](super::Histogram::difference), the
+/// exact `min` and `max` are unknown: they are narrowed to bucket boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    count: u64,
+    /// `u64::MAX` when empty.
+    min: u64,
+    /// `0` when empty.
+    max: u64,
+    sum: u128,
+    sum_of_squares: u128,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Summary {
+    /// The summary of no values.
+    pub const EMPTY: Summary = Summary {
+        count: 0,
+        min: u64::MAX,
+        max: 0,
+        sum: 0,
+        sum_of_squares: 0,
+    };
+
+    /// Builds a summary from its parts. `min` and `max` are ignored if `count` is 0.
+    pub(crate) fn from_parts(
+        count: u64,
+        min: u64,
+        max: u64,
+        sum: u128,
+        sum_of_squares: u128,
+    ) -> Self {
+        if count == 0 {
+            return Self::EMPTY;
+        }
+        Self {
+            count,
+            min,
+            max,
+            sum,
+            sum_of_squares,
+        }
+    }
+
+    /// Builds the summary of a slot holding `buckets` from its stored parts.
+    ///
+    /// Returns `None` if `buckets` are all empty, or if `min` and `max` are not within the
+    /// non-empty buckets.
+    pub(crate) fn from_stored(
+        scale: &impl BucketScale,
+        buckets: &[u64],
+        min: u64,
+        max: u64,
+        sum: u128,
+        sum_of_squares: u128,
+    ) -> Option<Self> {
+        let first = buckets.iter().position(|&c| c > 0)?;
+        let last = buckets.iter().rposition(|&c| c > 0)?;
+
+        let low = scale.bucket_min_value(first);
+        let high = scale.bucket_max_value(last);
+        if min < low || max > high || min > max {
+            return None;
+        }
+
+        let count = buckets.iter().sum();
+        Some(Self::from_parts(count, min, max, sum, sum_of_squares))
+    }
+
+    /// Returns the number of values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the smallest value, or `None` if there is no value.
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Returns the largest value, or `None` if there is no value.
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Returns the sum of the values.
+    pub fn sum(&self) -> u128 {
+        self.sum
+    }
+
+    /// Returns the sum of the squares of the values, saturating at `u128::MAX`.
+    pub fn sum_of_squares(&self) -> u128 {
+        self.sum_of_squares
+    }
+
+    /// Returns the arithmetic mean, or `None` if there is no value.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Returns the population variance, or `None` if there is no value or `sum_of_squares`
+    /// saturated.
+    pub fn variance(&self) -> Option<f64> {
+        if self.count == 0 || self.sum_of_squares == u128::MAX {
+            return None;
+        }
+        let n = self.count as f64;
+        let mean = self.sum as f64 / n;
+        Some((self.sum_of_squares as f64 / n - mean * mean).max(0.0))
+    }
+
+    /// Returns the population standard deviation. See [`variance()`](Self::variance).
+    pub fn stddev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    /// Adds one value.
+    #[inline]
+    pub(crate) fn record(&mut self, value: u64) {
+        let v = value as u128;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += v;
+        self.sum_of_squares = self.sum_of_squares.saturating_add(v * v);
+    }
+
+    /// Adds `count` occurrences of `value`.
+    pub(crate) fn record_n(&mut self, value: u64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let v = value as u128;
+        let n = count as u128;
+        self.count += count;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum = self.sum.saturating_add(v * n);
+        self.sum_of_squares = self.sum_of_squares.saturating_add((v * v).saturating_mul(n));
+    }
+
+    /// Adds all values of `other`.
+    pub(crate) fn merge(&mut self, other: &Summary) {
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum = self.sum.saturating_add(other.sum);
+        self.sum_of_squares = self.sum_of_squares.saturating_add(other.sum_of_squares);
+    }
+
+    /// Removes the count, sum and sum of squares of `other`, leaving `min` and `max` unchanged.
+    ///
+    /// A saturated sum of squares stays saturated, since its true value is unknown.
+    pub(crate) fn subtract_totals(&mut self, other: &Summary) {
+        self.count = self.count.saturating_sub(other.count);
+        self.sum = self.sum.saturating_sub(other.sum);
+        if self.sum_of_squares != u128::MAX {
+            self.sum_of_squares = self.sum_of_squares.saturating_sub(other.sum_of_squares);
+        }
+        if self.count == 0 {
+            *self = Self::EMPTY;
+        }
+    }
+
+    /// Sets `min` and `max`, e.g. after they were recomputed from the remaining slots.
+    pub(crate) fn set_extremes(&mut self, min: u64, max: u64) {
+        self.min = min;
+        self.max = max;
+    }
+
+    /// Returns the raw `min` and `max`, which are `u64::MAX` and `0` when empty.
+    pub(crate) fn extremes(&self) -> (u64, u64) {
+        (self.min, self.max)
+    }
+
+    /// Narrows `min` to the first and `max` to the last of the non-empty `buckets`, and takes
+    /// the count from them.
+    ///
+    /// Used after removing values whose exact `min` and `max` are unknown: the result is a
+    /// bound within one bucket of the true value. If `min` and `max` are inconsistent, e.g.
+    /// `self` is empty while `buckets` are not, they become the lower bound of the first and the
+    /// upper bound of the last non-empty bucket. `min <= max` always holds afterwards.
+    pub(crate) fn clamp_to_buckets(&mut self, scale: &impl BucketScale, buckets: &[u64]) {
+        let first = buckets.iter().position(|&c| c > 0);
+        let last = buckets.iter().rposition(|&c| c > 0);
+        let (Some(first), Some(last)) = (first, last) else {
+            *self = Self::EMPTY;
+            return;
+        };
+
+        let low = scale.bucket_min_value(first);
+        let high = scale.bucket_max_value(last);
+        self.count = buckets.iter().sum();
+        if self.min <= self.max {
+            self.min = self.min.clamp(low, scale.bucket_max_value(first));
+            self.max = self.max.clamp(scale.bucket_min_value(last), high);
+        }
+        if self.min > self.max {
+            (self.min, self.max) = (low, high);
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "[count: 0]");
+        }
+        write!(
+            f,
+            "[count: {}, min: {}, max: {}, mean: {:.2}",
+            self.count,
+            self.min,
+            self.max,
+            self.mean().unwrap_or_default()
+        )?;
+        if let Some(stddev) = self.stddev() {
+            write!(f, ", stddev: {:.2}", stddev)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::LOG_SCALE;
+
+    #[test]
+    fn test_record() {
+        let mut s = Summary::default();
+        assert_eq!(s.min(), None);
+        assert_eq!(s.max(), None);
+        assert_eq!(s.mean(), None);
+        assert_eq!(s.stddev(), None);
+
+        for v in [2, 4, 4, 4, 5, 5, 7, 9] {
+            s.record(v);
+        }
+        assert_eq!(s.count(), 8);
+        assert_eq!(s.min(), Some(2));
+        assert_eq!(s.max(), Some(9));
+        assert_eq!(s.sum(), 40);
+        assert_eq!(s.sum_of_squares(), 232);
+        assert_eq!(s.mean(), Some(5.0));
+        assert_eq!(s.variance(), Some(4.0));
+        assert_eq!(s.stddev(), Some(2.0));
+        assert_eq!(
+            s.to_string(),
+            "[count: 8, min: 2, max: 9, mean: 5.00, stddev: 2.00]"
+        );
+    }
+
+    #[test]
+    fn test_large_values() {
+        let mut s = Summary::default();
+        s.record(u64::MAX);
+        s.record(u64::MAX);
+        assert_eq!(s.sum(), 2 * u64::MAX as u128);
+        assert_eq!(s.sum_of_squares(), u128::MAX);
+        assert_eq!(s.variance(), None);
+        assert_eq!(s.mean(), Some(u64::MAX as f64));
+        assert!(!s.to_string().contains("stddev"));
+
+        // A saturated sum of squares stays saturated.
+        let mut one = Summary::default();
+        one.record(u64::MAX);
+        s.subtract_totals(&one);
+        assert_eq!(s.sum_of_squares(), u128::MAX);
+        assert_eq!(s.count(), 1);
+    }
+
+    #[test]
+    fn test_record_n() {
+        let mut a = Summary::default();
+        a.record_n(7, 0);
+        assert_eq!(a, Summary::EMPTY);
+
+        let mut b = Summary::default();
+        for _ in 0..3 {
+            a.record(4096);
+        }
+        b.record_n(4096, 3);
+        assert_eq!(a, b);
+
+        b.record_n(u64::MAX, u64::MAX - 3);
+        assert_eq!(b.count(), u64::MAX);
+        assert_eq!(b.sum_of_squares(), u128::MAX);
+    }
+
+    #[test]
+    fn test_merge_and_subtract() {
+        let mut a = Summary::default();
+        a.record(10);
+        a.record(20);
+        let mut b = Summary::default();
+        b.record(5);
+
+        a.merge(&b);
+        assert_eq!(
+            (a.count(), a.min(), a.max(), a.sum()),
+            (3, Some(5), Some(20), 35)
+        );
+
+        a.subtract_totals(&b);
+        assert_eq!((a.count(), a.sum(), a.sum_of_squares()), (2, 30, 500));
+
+        a.subtract_totals(&a.clone());
+        assert_eq!(a, Summary::EMPTY);
+    }
+
+    #[test]
+    fn test_clamp_to_buckets() {
+        let mut s = Summary::default();
+        s.record(1);
+        s.record(100);
+        s.record(5000);
+
+        // Remove 1 and 5000 without knowing which values they were.
+        let mut buckets = vec![0; LOG_SCALE.num_buckets()];
+        buckets[LOG_SCALE.calculate_bucket(100)] = 1;
+        s.clamp_to_buckets(&LOG_SCALE, &buckets);
+
+        assert_eq!(s.count(), 1);
+        assert_eq!(s.min(), Some(96));
+        assert_eq!(s.max(), Some(111));
+
+        s.clamp_to_buckets(&LOG_SCALE, &[0; 252]);
+        assert_eq!(s, Summary::EMPTY);
+
+        // An empty summary of non-empty buckets takes their bounds.
+        buckets[LOG_SCALE.calculate_bucket(5000)] = 1;
+        let mut empty = Summary::EMPTY;
+        empty.clamp_to_buckets(&LOG_SCALE, &buckets);
+        assert_eq!(empty.count(), 2);
+        assert_eq!(empty.min(), Some(96));
+        assert_eq!(empty.max(), Some(5119));
+
+        // A summary of values above the buckets is narrowed to the first and last bucket.
+        let mut above = Summary::EMPTY;
+        above.record(1 << 40);
+        above.clamp_to_buckets(&LOG_SCALE, &buckets);
+        assert_eq!((above.min(), above.max()), (Some(111), Some(5119)));
+
+        // Inconsistent extremes within a single bucket take its bounds.
+        let mut single = Summary::from_parts(1, 110, 100, 0, 0);
+        single.clamp_to_buckets(&LOG_SCALE, &buckets[..=LOG_SCALE.calculate_bucket(100)]);
+        assert_eq!((single.min(), single.max()), (Some(96), Some(111)));
+    }
+}
@@ -0,0 +1,259 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use super::clock::Clock;
+use super::clock::SystemClock;
+use super::histogram::Histogram;
+use super::percentile_stats::PercentileStats;
+use super::summary::Summary;
+
+/// A sliding-window [`Histogram`] whose slots rotate automatically with time.
+///
+/// The window is made of `slots` slots of `slot_duration` each, e.g. 60 slots of 1 second for a
+/// one-minute window. There is no timer: on every [`record()`](Self::record) or query, the
+/// histogram first advances by the number of slot durations elapsed since the current slot
+/// started. After an idle gap longer than the whole window, all slots are cleared at once.
+///
+/// Slot boundaries are aligned to the creation time: slot `k` covers
+/// `[start + k * slot_duration, start + (k + 1) * slot_duration)`.
+///
+/// Time is read from a [`Clock`], [`SystemClock`] by default; tests can use
+/// [`ManualClock`](super::ManualClock) to run deterministically.
+#[derive(Debug)]
+pub struct WindowedHistogram<C: Clock = SystemClock, const WIDTH: usize = 3> {
+    histogram: Histogram<(), WIDTH>,
+    clock: C,
+    slot_duration: Duration,
+    /// Start time of the current (newest) slot.
+    current_slot_start: Instant,
+}
+
+impl<const WIDTH: usize> WindowedHistogram<SystemClock, WIDTH> {
+    /// Creates a window of `slots` slots of `slot_duration` each, using the system clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` is 0 or `slot_duration` is zero.
+    pub fn new(slots: usize, slot_duration: Duration) -> Self {
+        Self::with_clock(SystemClock, slots, slot_duration)
+    }
+}
+
+impl<C: Clock, const WIDTH: usize> WindowedHistogram<C, WIDTH> {
+    /// Creates a window of `slots` slots of `slot_duration` each, reading time from `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` is 0 or `slot_duration` is zero.
+    pub fn with_clock(clock: C, slots: usize, slot_duration: Duration) -> Self {
+        assert!(!slot_duration.is_zero(), "slot_duration must be non-zero");
+
+        let current_slot_start = clock.now();
+        Self {
+            histogram: Histogram::with_slots(slots),
+            clock,
+            slot_duration,
+            current_slot_start,
+        }
+    }
+}
+
+impl<C: Clock, const WIDTH: usize> WindowedHistogram<C, WIDTH> {
+    /// Records a value to the slot covering the current time.
+    pub fn record(&mut self, value: u64) {
+        self.sync();
+        self.histogram.record(value);
+    }
+
+    /// Returns the total number of values recorded within the window.
+    pub fn total(&mut self) -> u64 {
+        self.histogram().total()
+    }
+
+    /// Calculates the value at the given percentile over the window.
+    ///
+    /// See [`Histogram::percentile()`].
+    pub fn percentile(&mut self, p: f64) -> u64 {
+        self.histogram().percentile(p)
+    }
+
+    /// Returns common percentile statistics over the window.
+    pub fn percentile_stats(&mut self) -> PercentileStats {
+        self.histogram().percentile_stats()
+    }
+
+    /// Returns the exact min, max, sum and sum of squares over the window.
+    pub fn summary(&mut self) -> Summary {
+        self.histogram().summary()
+    }
+
+    /// Returns the underlying histogram, advanced to the current time.
+    pub fn histogram(&mut self) -> &Histogram<(), WIDTH> {
+        self.sync();
+        &self.histogram
+    }
+
+    /// Returns the duration covered by one slot.
+    pub fn slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
+    /// Returns the duration covered by the whole window, saturating at [`Duration::MAX`].
+    pub fn window(&self) -> Duration {
+        u32::try_from(self.histogram.capacity())
+            .ok()
+            .and_then(|slots| self.slot_duration.checked_mul(slots))
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Advances the histogram by the number of slot durations elapsed since the current slot
+    /// started.
+    fn sync(&mut self) {
+        let elapsed = self.clock.now().saturating_duration_since(self.current_slot_start);
+        let slot_nanos = self.slot_duration.as_nanos();
+        let elapsed_slots = elapsed.as_nanos() / slot_nanos;
+        if elapsed_slots == 0 {
+            return;
+        }
+
+        // Advancing `capacity` times evicts every slot: skip the rest of a long idle gap.
+        let steps = elapsed_slots.min(self.histogram.capacity() as u128);
+        for _ in 0..steps {
+            self.histogram.advance(());
+        }
+
+        let skipped = elapsed_slots * slot_nanos;
+        self.current_slot_start += Duration::new(
+            (skipped / 1_000_000_000) as u64,
+            (skipped % 1_000_000_000) as u32,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::ManualClock;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn windowed(clock: &ManualClock, slots: usize) -> WindowedHistogram<ManualClock> {
+        WindowedHistogram::with_clock(clock.clone(), slots, SECOND)
+    }
+
+    #[test]
+    fn test_record_within_slot() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 3);
+
+        hist.record(1);
+        clock.advance(Duration::from_millis(999));
+        hist.record(2);
+
+        assert_eq!(hist.total(), 2);
+        assert_eq!(hist.histogram().active_slot_count(), 1);
+        assert_eq!(hist.window(), Duration::from_secs(3));
+        assert_eq!(hist.slot_duration(), SECOND);
+    }
+
+    #[test]
+    fn test_advances_one_slot_per_duration() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 3);
+
+        hist.record(10);
+        clock.advance(SECOND);
+        hist.record(20);
+        clock.advance(SECOND);
+        hist.record(30);
+
+        assert_eq!(hist.histogram().active_slot_count(), 3);
+        assert_eq!(hist.total(), 3);
+
+        // The slot holding 10 falls out of the window.
+        clock.advance(SECOND);
+        assert_eq!(hist.total(), 2);
+        assert_eq!(hist.percentile(0.0), 20);
+    }
+
+    #[test]
+    fn test_query_advances_lazily() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 2);
+
+        hist.record(10);
+        clock.advance(Duration::from_millis(2500));
+
+        // No record since, but the query still sees the window has moved past the value.
+        assert_eq!(hist.percentile_stats().samples, 0);
+    }
+
+    #[test]
+    fn test_skip_several_slots() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 5);
+
+        hist.record(10);
+        clock.advance(Duration::from_secs(3));
+        hist.record(20);
+
+        // 10 is three slots old, still in the window of 5.
+        assert_eq!(hist.total(), 2);
+        assert_eq!(hist.histogram().active_slot_count(), 4);
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(hist.total(), 1);
+        assert_eq!(hist.percentile(0.5), 20);
+    }
+
+    #[test]
+    fn test_idle_gap_longer_than_window() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 3);
+
+        for v in 0..100 {
+            hist.record(v);
+        }
+
+        clock.advance(Duration::from_secs(1_000_000));
+        assert_eq!(hist.total(), 0);
+
+        hist.record(7);
+        assert_eq!(hist.total(), 1);
+    }
+
+    #[test]
+    fn test_slots_stay_aligned() {
+        let clock = ManualClock::new();
+        let mut hist = windowed(&clock, 2);
+
+        hist.record(1);
+        clock.advance(Duration::from_millis(1500));
+        hist.record(2);
+
+        // The second slot started at 1s, not at 1.5s: it ends at 2s.
+        clock.advance(Duration::from_millis(500));
+        hist.record(3);
+        assert_eq!(hist.total(), 2);
+        assert_eq!(hist.percentile(0.0), 2);
+    }
+
+    #[test]
+    fn test_system_clock() {
+        let mut hist: WindowedHistogram = WindowedHistogram::new(60, SECOND);
+        hist.record(5);
+        assert_eq!(hist.total(), 1);
+    }
+
+    #[test]
+    fn test_window_saturates() {
+        let hist: WindowedHistogram = WindowedHistogram::new(2, Duration::MAX / 2 + SECOND);
+        assert_eq!(hist.window(), Duration::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "slot_duration must be non-zero")]
+    fn test_zero_slot_duration_panics() {
+        let _: WindowedHistogram = WindowedHistogram::new(1, Duration::ZERO);
+    }
+}